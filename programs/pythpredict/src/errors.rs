@@ -172,6 +172,86 @@ pub enum PredictionMarketError {
     #[msg("Market is closed for betting")]
     MarketClosed,
 
+    #[msg("Invalid market metadata")]
+    InvalidMetadata,
+
+    #[msg("Market still has unclaimed positions")]
+    UnclaimedPositions,
+
+    #[msg("Position still has funds to claim")]
+    PositionNotSettled,
+
+    #[msg("Protocol is paused")]
+    ProtocolPaused,
+
+    #[msg("Market is not cancelled")]
+    MarketNotCancelled,
+
+    #[msg("Funding deadline has passed")]
+    FundingDeadlinePassed,
+
+    #[msg("Market template is disabled")]
+    TemplateDisabled,
+
+    #[msg("Registry page is full")]
+    RegistryPageFull,
+
+    #[msg("Invalid registry page account")]
+    InvalidRegistryPage,
+
+    #[msg("Collateral mint is not on the allowlist")]
+    CollateralNotAllowed,
+
+    #[msg("Collateral mint uses an unsupported Token-2022 extension")]
+    UnsupportedMintExtension,
+
+    #[msg("A token account is required unless the market uses native SOL")]
+    MissingTokenAccount,
+
+    #[msg("Creator bond has already been returned or slashed")]
+    BondAlreadySettled,
+
+    #[msg("Creator bond must be returned or slashed before closing")]
+    BondOutstanding,
+
+    #[msg("Stake would exceed the per-user limit for this market")]
+    UserStakeLimitExceeded,
+
+    #[msg("Batch account does not match the expected market, position or vault")]
+    InvalidBatchAccount,
+
+    #[msg("Betting delegate has expired")]
+    DelegateExpired,
+
+    #[msg("Bet exceeds the betting delegate's per-bet or total cap")]
+    DelegateLimitExceeded,
+
+    #[msg("Referrer does not match the user's attribution")]
+    InvalidReferrer,
+
+    #[msg("Market records stakes as outcome tokens, not positions")]
+    TokenizedMarket,
+
+    #[msg("Market does not use outcome tokens")]
+    MarketNotTokenized,
+
+    #[msg("Order book has no room for another order on this side")]
+    OrderBookFull,
+
+    #[msg("Order not found")]
+    OrderNotFound,
+
+    #[msg("Explicit market nonces must leave the counter bit clear")]
+    InvalidMarketNonce,
+
+    #[msg("A filled or evicted maker's trader balance account was not passed")]
+    MissingTraderBalance,
+
+    #[msg("Creator bond is still within its dispute window")]
+    BondDisputeWindow,
+
+    #[msg("The position's referral share must be released or refunded first")]
+    ReferralFeesOutstanding,
 
 }
 impl PredictionMarketError {
@@ -0,0 +1,179 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct MarketPaused {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub scope: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketUnpaused {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolPaused {
+    pub authority: Pubkey,
+    pub scope: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProtocolUnpaused {
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub market: Pubkey,
+    pub better: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BondReturned {
+    pub market: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct BondSlashed {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionSold {
+    pub market: Pubkey,
+    pub better: Pubkey,
+    pub outcome: u8,
+    pub amount: u64,
+    pub proceeds: u64,
+    pub exit_fee: u64,
+}
+
+#[event]
+pub struct DelegateApproved {
+    pub user: Pubkey,
+    pub delegate: Pubkey,
+    pub total_cap: u64,
+    pub per_bet_cap: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct DelegateRevoked {
+    pub user: Pubkey,
+    pub delegate: Pubkey,
+}
+
+#[event]
+pub struct ReferralFeesClaimed {
+    pub referrer: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReferralFeesReleased {
+    pub market: Pubkey,
+    pub better: Pubkey,
+    pub referrer: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub market: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub outcome: u8,
+    pub amount: u64,
+}
+
+#[event]
+pub struct OutcomeTokensRedeemed {
+    pub market: Pubkey,
+    pub holder: Pubkey,
+    pub outcome: u8,
+    pub amount: u64,
+    pub payout: u64,
+}
+
+#[event]
+pub struct CompleteSetsMinted {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CompleteSetsMerged {
+    pub market: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WinningSharesRedeemed {
+    pub market: Pubkey,
+    pub holder: Pubkey,
+    pub outcome: u8,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CancelledSharesRedeemed {
+    pub market: Pubkey,
+    pub holder: Pubkey,
+    pub outcome: u8,
+    pub shares: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct OrderPosted {
+    pub market: Pubkey,
+    pub outcome: u8,
+    /// None when nothing was left to rest on the book.
+    pub order_id: Option<u64>,
+    pub owner: Pubkey,
+    pub side: u8,
+    pub price_bps: u16,
+    pub quantity: u64,
+    pub filled: u64,
+    pub cost: u64,
+}
+
+#[event]
+pub struct OrderCancelled {
+    pub market: Pubkey,
+    pub outcome: u8,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub refunded: u64,
+}
+
+#[event]
+pub struct TradesSettled {
+    pub market: Pubkey,
+    pub outcome: u8,
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub collateral: u64,
+}
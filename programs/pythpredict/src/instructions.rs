@@ -1,14 +1,18 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
-use pyth_sdk_solana::{Price, PriceFeed};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{
+    self, Approve, Burn, CloseAccount, Mint, MintTo, Revoke, SyncNative, TokenAccount,
+    TokenInterface, TransferChecked,
+};
 
 use crate::errors::PredictionMarketError;
+use crate::events::*;
 use crate::state::*;
-use anchor_lang::Space;
-
-// pythpredict/src/instructions.rs (anywhere near top-level module funcs)
-use core::cell::RefCell;
-use std::rc::Rc;
 
 /// Minimal parser matching your test’s hard-coded offsets.
 /// Returns (price, expo, conf) as raw Pyth values.
@@ -28,11 +32,36 @@ pub fn parse_pyth_price_alternative(feed_ai: &AccountInfo<'_>) -> Result<(i64, i
     Ok((price, expo, conf))
 }
 
+/// Publish time of the aggregate price in the same feed account.
+pub fn parse_pyth_publish_time(feed_ai: &AccountInfo<'_>) -> Result<i64> {
+    let data = feed_ai.try_borrow_data()
+        .map_err(|_| error!(PredictionMarketError::OracleError))?;
+
+    if data.len() < 228 {
+        return Err(error!(PredictionMarketError::OracleError));
+    }
+    Ok(i64::from_le_bytes(data[96..104].try_into().unwrap()))
+}
+
+/// Confidence interval as basis points of a positive price, the unit
+/// `Market::oracle_confidence` is configured in.
+pub fn confidence_bps(price: i64, conf: u64) -> u64 {
+    if price <= 0 {
+        return u64::MAX;
+    }
+    let bps = (conf as u128) * 10_000 / (price as u128);
+    u64::try_from(bps).unwrap_or(u64::MAX)
+}
+
 pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
     let market = &mut ctx.accounts.market;
     let clock = Clock::get()?;
 
     require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(
+        market.market_status == MarketStatus::Active,
+        PredictionMarketError::InvalidMarketStatus
+    );
     require!(clock.unix_timestamp >= market.settle_time, PredictionMarketError::SettlementTimeNotReached);
 
     let (price, expo, _conf) = parse_pyth_price_alternative(&ctx.accounts.pyth_feed)?;
@@ -41,13 +70,16 @@ pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
     let current_price = price;
 
     let initial_price = market.target_price;
-    let winning_outcome = if current_price != initial_price { 0 } else { 1 }; // YES if moved
+    let winning_outcome = if market.resolves_yes(current_price) { 0 } else { 1 };
 
     market.is_resolved = true;
     market.winning_outcome = Some(winning_outcome);
     market.final_price = Some(current_price);
     market.oracle_last_update = clock.unix_timestamp;
     market.market_status = MarketStatus::Resolved;
+    market.settled_at = clock.unix_timestamp;
+
+    ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
 
     msg!(
         "Market resolved! Initial: {}, Final: {}, Expo: {}, Winner: {}",
@@ -57,405 +89,4080 @@ pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
 }
 
 
-// ===== INITIALIZE MARKET WITH ENHANCED CONTROLS =====
+// ===== PROTOCOL CONFIG & EMERGENCY PAUSE =====
 #[derive(Accounts)]
-#[instruction(market_nonce: u64)]
-pub struct InitializeMarket<'info> {
+pub struct InitializeProtocolConfig<'info> {
     #[account(
         init,
-        payer = creator,
-        space = Market::SIZE,
-        seeds = [b"market", creator.key().as_ref(), &market_nonce.to_le_bytes()],
+        payer = admin,
+        space = ProtocolConfig::SIZE,
+        seeds = [b"protocol_config"],
         bump
     )]
-    pub market: Account<'info, Market>,
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-    #[account(
-        init,
-        payer = creator,
-        token::mint = collateral_mint,
-        token::authority = market,
-        seeds = [b"yes_vault", market.key().as_ref()],
-        bump
-    )]
-    pub yes_vault: Account<'info, TokenAccount>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Pythpredict>,
 
-    #[account(
-        init,
-        payer = creator,
-        token::mint = collateral_mint,
-        token::authority = market,
-        seeds = [b"no_vault", market.key().as_ref()],
-        bump
-    )]
-    pub no_vault: Account<'info, TokenAccount>,
+    /// Only the program's upgrade authority may become the first admin.
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ PredictionMarketError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
 
-    #[account(
-        init,
-        payer = creator,
-        token::mint = collateral_mint,
-        token::authority = market,  // Changed from fee_collector to market for consistency
-        seeds = [b"fee_vault", market.key().as_ref()],
-        bump
-    )]
-    pub fee_vault: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn initialize_protocol_config(
+    ctx: Context<InitializeProtocolConfig>,
+    pauser: Pubkey,
+) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+
+    config.admin = ctx.accounts.admin.key();
+    config.pauser = pauser;
+    config.global_paused = false;
+    config.global_pause_scope = 0;
+    config.bump = ctx.bumps.protocol_config;
+    config.max_open_positions = 0;
+    config.referral_share_bps = 0;
+
+    msg!("Protocol config initialized, admin: {}, pauser: {}", config.admin, pauser);
+
+    Ok(())
+}
 
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
     #[account(
-        init_if_needed,
-        payer = creator,
-        space = FeeCollector::SIZE,
-        seeds = [b"fee_collector"],
-        bump
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        has_one = admin @ PredictionMarketError::Unauthorized
     )]
-    pub fee_collector: Account<'info, FeeCollector>,
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-    pub collateral_mint: Account<'info, Mint>,
-
-    /// CHECK: This account is the Pyth oracle price feed. It's validated at runtime when reading price data.
-    pub pyth_feed: AccountInfo<'info>,
+    pub admin: Signer<'info>,
+}
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+pub fn set_pauser(ctx: Context<UpdateProtocolConfig>, pauser: Pubkey) -> Result<()> {
+    ctx.accounts.protocol_config.pauser = pauser;
+    msg!("Pauser set to {}", pauser);
+    Ok(())
 }
 
-pub fn initialize_market(
-    ctx: Context<InitializeMarket>,
-    market_nonce: u64,
-    initial_price: i64,
-    target_change_bps: i64,
-    settle_time: i64,
-    resolver_authority: Option<Pubkey>,
-) -> Result<()> {
-    let market = &mut ctx.accounts.market;
-    let clock = Clock::get()?;
+/// Caps how many unsettled positions one user may hold across all markets (0 = unlimited).
+pub fn set_max_open_positions(ctx: Context<UpdateProtocolConfig>, max_open_positions: u32) -> Result<()> {
+    ctx.accounts.protocol_config.max_open_positions = max_open_positions;
+    msg!("Max open positions per user set to {}", max_open_positions);
+    Ok(())
+}
 
-    // Validate settlement time with proper error messages
-    let params = MarketParams::default();
-    require!(
-        settle_time > clock.unix_timestamp + params.min_settlement_time,
-        PredictionMarketError::SettlementTimeTooSoon  // Use appropriate error
-    );
+/// Share of each bet fee paid to the bettor's referrer, in basis points.
+pub fn set_referral_share(ctx: Context<UpdateProtocolConfig>, referral_share_bps: u16) -> Result<()> {
     require!(
-        settle_time < clock.unix_timestamp + params.max_settlement_time,
-        PredictionMarketError::SettlementTimeTooFar  // Use appropriate error
+        referral_share_bps <= ProtocolConfig::MAX_REFERRAL_SHARE_BPS,
+        PredictionMarketError::InvalidParameter
     );
+    ctx.accounts.protocol_config.referral_share_bps = referral_share_bps;
+    msg!("Referral share set to {} bps", referral_share_bps);
+    Ok(())
+}
 
-    // Calculate target price based on initial price and target change
-    // For zero-target markets, we use the initial price as both initial and target
-    let target_price = if target_change_bps == 0 {
-        initial_price // Zero target means we're tracking if price moves at all
-    } else {
-        initial_price + (initial_price * target_change_bps / 10000)
-    };
+#[derive(Accounts)]
+pub struct SetProtocolPause<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.is_pause_authority(&authority.key()) @ PredictionMarketError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-    // Initialize market
-    market.creator = ctx.accounts.creator.key();
-    market.pyth_feed = ctx.accounts.pyth_feed.key();
-    market.target_price = initial_price; // Store initial price here
-    market.settle_time = settle_time;
-    market.yes_pool = 0;
-    market.no_pool = 0;
-    market.collateral_mint = ctx.accounts.collateral_mint.key();
-    market.is_resolved = false;
-    market.winning_outcome = None;
-    market.nonce = market_nonce;
-    market.bump = ctx.bumps.market;
-    market.resolver_authority = resolver_authority.unwrap_or(ctx.accounts.creator.key());
-    market.total_volume = 0;
-    market.fee_bps = 100; // 1% default fee
-    market.final_price = None;
+    pub authority: Signer<'info>,
+}
 
-    // Initialize extended fields
-    market.total_fees_collected = 0;
-    market.fee_collector = ctx.accounts.fee_collector.key();
-    market.oracle_confidence = 500; // 5% max confidence
-    market.min_bet_amount = 100_000; // 0.1 token minimum
-    market.max_bet_amount = 1_000_000_000_000; // 1M tokens max
-    market.market_status = MarketStatus::Active; // Start as Active, not PendingLiquidity
-    market.created_at = clock.unix_timestamp;
-    market.description = [0u8; 128]; // To be set later if needed
-    market.category = MarketCategory::Crypto;
-    market.oracle_last_update = clock.unix_timestamp;
-    market.emergency_paused = false;
-    market.min_liquidity = 0; // Set to 0 to allow immediate betting
-    market.liquidity_locked_until = settle_time;
+pub fn pause_protocol(ctx: Context<SetProtocolPause>, scope: u8) -> Result<()> {
+    require!(PauseScope::is_valid(scope), PredictionMarketError::InvalidParameter);
 
-    // Store the actual target price in a new field if we need it
-    // For now, we'll use final_price field to track the target
-    if target_change_bps != 0 {
-        // We'll track the target separately if needed
-        // For zero-target markets, any change from initial_price is YES
-    }
+    let config = &mut ctx.accounts.protocol_config;
+    config.global_paused = true;
+    config.global_pause_scope = scope;
 
-    // Initialize fee collector if needed
-    if ctx.accounts.fee_collector.authority == Pubkey::default() {
-        let fee_collector = &mut ctx.accounts.fee_collector;
-        fee_collector.authority = ctx.accounts.creator.key();
-        fee_collector.total_fees_collected = 0;
-        fee_collector.treasury = ctx.accounts.creator.key();
-        fee_collector.fee_distribution = FeeDistribution {
-            treasury_bps: 5000,   // 50% to treasury
-            liquidity_bps: 3000,  // 30% to LPs
-            creator_bps: 2000,    // 20% to market creator
-        };
-    }
+    emit!(ProtocolPaused {
+        authority: ctx.accounts.authority.key(),
+        scope,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    msg!("Protocol paused, scope: {:#05b}", scope);
 
-    msg!("Market initialized with initial price: {}", initial_price);
-    msg!("Target change: {} bps", target_change_bps);
-    msg!("Fee collector: {}", ctx.accounts.fee_collector.key());
-    msg!("Market status: {:?}", market.market_status);
+    Ok(())
+}
+
+pub fn unpause_protocol(ctx: Context<SetProtocolPause>) -> Result<()> {
+    let config = &mut ctx.accounts.protocol_config;
+    config.global_paused = false;
+    config.global_pause_scope = 0;
+
+    emit!(ProtocolUnpaused {
+        authority: ctx.accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    msg!("Protocol unpaused");
 
     Ok(())
 }
 
-// ===== PLACE BET WITH FEE HANDLING =====
 #[derive(Accounts)]
-pub struct Bet<'info> {
+pub struct SetMarketPause<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
 
     #[account(
-        init_if_needed,
-        payer = better,
-        space = Position::SIZE,
-        seeds = [b"position", market.key().as_ref(), better.key().as_ref()],
-        bump
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = protocol_config.is_pause_authority(&authority.key()) @ PredictionMarketError::Unauthorized
     )]
-    pub position: Account<'info, Position>,
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-    #[account(
-        mut,
-        constraint = user_token_account.owner == better.key(),
-        constraint = user_token_account.mint == market.collateral_mint
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
+}
 
-    #[account(
-        mut,
-        seeds = [b"yes_vault", market.key().as_ref()],
-        bump
-    )]
-    pub yes_vault: Account<'info, TokenAccount>,
+pub fn pause_market(ctx: Context<SetMarketPause>, scope: u8) -> Result<()> {
+    require!(PauseScope::is_valid(scope), PredictionMarketError::InvalidParameter);
 
-    #[account(
-        mut,
-        seeds = [b"no_vault", market.key().as_ref()],
-        bump
-    )]
-    pub no_vault: Account<'info, TokenAccount>,
+    let market = &mut ctx.accounts.market;
+    market.emergency_paused = true;
+    market.pause_scope = scope;
 
-    #[account(
-        mut,
-        seeds = [b"fee_vault", market.key().as_ref()],
-        bump
-    )]
-    pub fee_vault: Account<'info, TokenAccount>,
+    emit!(MarketPaused {
+        market: market.key(),
+        authority: ctx.accounts.authority.key(),
+        scope,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    msg!("Market paused, scope: {:#05b}", scope);
 
-    #[account(mut)]
-    pub better: Signer<'info>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    Ok(())
 }
 
-pub fn place_bet(ctx: Context<Bet>, amount: u64, outcome: Outcome) -> Result<()> {
+pub fn unpause_market(ctx: Context<SetMarketPause>) -> Result<()> {
     let market = &mut ctx.accounts.market;
-    let position = &mut ctx.accounts.position;
-    let clock = Clock::get()?;
+    market.emergency_paused = false;
+    market.pause_scope = 0;
 
-    // Validate market state
-    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
-    require!(clock.unix_timestamp < market.settle_time, PredictionMarketError::MarketClosed);
-    require!(!market.emergency_paused, PredictionMarketError::MarketPaused);
+    emit!(MarketUnpaused {
+        market: market.key(),
+        authority: ctx.accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    msg!("Market unpaused");
 
-    // Check if market is active (remove the is_active() check since we set it to Active on init)
-    require!(
-        market.market_status == MarketStatus::Active || market.market_status == MarketStatus::PendingLiquidity,
-        PredictionMarketError::MarketNotActive
-    );
+    Ok(())
+}
 
-    // Validate bet amount
-    require!(amount > 0, PredictionMarketError::InvalidAmount);
-    require!(
-        amount >= market.min_bet_amount,
-        PredictionMarketError::BetTooSmall
-    );
-    require!(
-        amount <= market.max_bet_amount,
-        PredictionMarketError::BetTooLarge
-    );
+// ===== TOKEN HELPERS =====
+/// Token-2022 mint extensions that collateral may carry. Everything else
+/// (transfer hooks, permanent delegates, non-transferable, confidential
+/// transfers, default-frozen accounts, ...) is rejected at market creation.
+pub const SUPPORTED_MINT_EXTENSIONS: &[ExtensionType] = &[
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+];
 
-    // Calculate fee
-    let (fee, amount_after_fee) = calculate_fee(amount, market.fee_bps)?;
+fn validate_collateral_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
 
-    // Transfer fee to fee vault
-    if fee > 0 {
-        let fee_transfer_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_token_account.to_account_info(),
-                to: ctx.accounts.fee_vault.to_account_info(),
-                authority: ctx.accounts.better.to_account_info(),
-            },
-        );
-        token::transfer(fee_transfer_ctx, fee)?;
+    let data = mint_info.try_borrow_data()?;
+    check_mint_extensions(&data)
+}
 
-        market.total_fees_collected = market.total_fees_collected
-            .checked_add(fee)
-            .ok_or(PredictionMarketError::MathOverflow)?;
+/// Checks Token-2022 mint data against `SUPPORTED_MINT_EXTENSIONS`.
+pub fn check_mint_extensions(mint_data: &[u8]) -> Result<()> {
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data)?;
+    for extension in state.get_extension_types()? {
+        require!(
+            SUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            PredictionMarketError::UnsupportedMintExtension
+        );
     }
+    Ok(())
+}
 
-    // Transfer bet amount to appropriate vault
-    let vault = match outcome {
-        Outcome::Yes => &ctx.accounts.yes_vault,
-        Outcome::No => &ctx.accounts.no_vault,
+/// `transfer_checked` from a user's token account into a market vault.
+/// Returns what actually landed in `to`, which is less than `amount` when
+/// the mint charges a transfer fee.
+fn transfer_in<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    authority_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let balance_before = to.amount;
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority,
+        },
+        authority_seeds,
+    );
+    token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)?;
+
+    to.reload()?;
+    to.amount
+        .checked_sub(balance_before)
+        .ok_or_else(|| PredictionMarketError::MathOverflow.into())
+}
+
+/// Moves `amount` from the better into a market vault: from their token
+/// account when given, otherwise as lamports wrapped into a native SOL vault.
+/// Returns what landed in the vault. `authority_seeds` are only needed when a
+/// PDA signs as the token account's delegate.
+#[allow(clippy::too_many_arguments)]
+fn deposit<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: Option<&InterfaceAccount<'info, TokenAccount>>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    authority_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let Some(from) = from else {
+        require_keys_eq!(
+            mint.key(),
+            native_mint::ID,
+            PredictionMarketError::MissingTokenAccount
+        );
+        return wrap_lamports(token_program, system_program, to, authority, amount);
     };
+    transfer_in(token_program, mint, from, to, authority, authority_seeds, amount)
+}
 
+/// Sends lamports straight into a wrapped-SOL vault and syncs its token balance.
+fn wrap_lamports<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    system_program: &Program<'info, System>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    payer: AccountInfo<'info>,
+    amount: u64,
+) -> Result<u64> {
     let transfer_ctx = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        Transfer {
-            from: ctx.accounts.user_token_account.to_account_info(),
+        system_program.to_account_info(),
+        system_program::Transfer {
+            from: payer,
             to: vault.to_account_info(),
-            authority: ctx.accounts.better.to_account_info(),
         },
     );
-    token::transfer(transfer_ctx, amount_after_fee)?;
+    system_program::transfer(transfer_ctx, amount)?;
 
-    // Store current odds before updating pools
-    let (yes_odds, no_odds) = market.calculate_odds();
+    let sync_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        SyncNative {
+            account: vault.to_account_info(),
+        },
+    );
+    token_interface::sync_native(sync_ctx)?;
 
-    // Update market pools
-    match outcome {
-        Outcome::Yes => {
-            market.yes_pool = market.yes_pool
-                .checked_add(amount_after_fee)
-                .ok_or(PredictionMarketError::MathOverflow)?;
-        }
-        Outcome::No => {
-            market.no_pool = market.no_pool
-                .checked_add(amount_after_fee)
-                .ok_or(PredictionMarketError::MathOverflow)?;
-        }
-    }
+    vault.reload()?;
+    Ok(amount)
+}
 
-    // Update position
-    if position.market == Pubkey::default() {
-        position.market = market.key();
-        position.better = ctx.accounts.better.key();
-        position.claimed = false;
-        position.yes_amount = 0;
-        position.no_amount = 0;
-        position.total_invested = 0;
-        position.pending_payout = 0;
-    }
+/// Closes a market-owned token account, paying its rent (and, for wrapped
+/// SOL, its balance) to `destination` as lamports.
+fn unwrap_to<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    account: &InterfaceAccount<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    market: &Account<'info, Market>,
+) -> Result<()> {
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[market_seeds];
 
-    match outcome {
-        Outcome::Yes => {
-            position.yes_amount = position.yes_amount
-                .checked_add(amount_after_fee)
-                .ok_or(PredictionMarketError::MathOverflow)?;
-        }
-        Outcome::No => {
-            position.no_amount = position.no_amount
-                .checked_add(amount_after_fee)
-                .ok_or(PredictionMarketError::MathOverflow)?;
+    let close_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: account.to_account_info(),
+            destination,
+            authority: market.to_account_info(),
+        },
+        signer,
+    );
+    token_interface::close_account(close_ctx)
+}
+
+/// Pays a market vault's whole balance to `to` and closes the vault, refunding
+/// its rent to `rent_destination`. Without `to`, a native SOL vault's balance
+/// leaves as lamports on close. Returns the amount paid out.
+fn drain_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    to: Option<AccountInfo<'info>>,
+    rent_destination: AccountInfo<'info>,
+    market: &Account<'info, Market>,
+) -> Result<u64> {
+    let amount = vault.amount;
+    match to {
+        Some(to) if amount > 0 => {
+            let nonce_bytes = market.nonce.to_le_bytes();
+            let market_seeds: &[&[u8]] = &[
+                b"market",
+                market.creator.as_ref(),
+                &nonce_bytes,
+                &[market.bump],
+            ];
+            let signer = &[market_seeds];
+
+            let transfer_ctx = CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: vault.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to,
+                    authority: market.to_account_info(),
+                },
+                signer,
+            );
+            token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)?;
         }
+        // Closing a wrapped-SOL vault pays its balance out as lamports
+        None => require!(
+            amount == 0 || market.is_native(),
+            PredictionMarketError::MissingTokenAccount
+        ),
+        _ => {}
     }
 
-    position.entry_odds_yes = (yes_odds * 10000.0) as u64;
-    position.entry_odds_no = (no_odds * 10000.0) as u64;
-    position.bet_timestamp = clock.unix_timestamp;
-    position.total_invested = position.total_invested
-        .checked_add(amount)
-        .ok_or(PredictionMarketError::MathOverflow)?;
+    harvest_withheld_fees(token_program, mint, &[vault.to_account_info()])?;
+    unwrap_to(token_program, vault, rent_destination, market)?;
+    Ok(amount)
+}
+
+/// Burns whatever is left in a share escrow and closes it, refunding its rent
+/// to `rent_destination`.
+fn close_share_escrow<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    share_mint: &InterfaceAccount<'info, Mint>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    rent_destination: AccountInfo<'info>,
+    market: &Account<'info, Market>,
+) -> Result<()> {
+    if escrow.amount > 0 {
+        let nonce_bytes = market.nonce.to_le_bytes();
+        let market_seeds: &[&[u8]] = &[
+            b"market",
+            market.creator.as_ref(),
+            &nonce_bytes,
+            &[market.bump],
+        ];
+        let signer = &[market_seeds];
+
+        let burn_ctx = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Burn {
+                mint: share_mint.to_account_info(),
+                from: escrow.to_account_info(),
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::burn(burn_ctx, escrow.amount)?;
+    }
+    unwrap_to(token_program, escrow, rent_destination, market)
+}
+
+/// Sweeps transfer fees withheld in market-owned token accounts back to the
+/// mint. Token-2022 refuses to close an account still holding withheld fees,
+/// so this runs before vaults are closed. Does nothing for mints without a
+/// transfer fee; for those that have one the mint must be passed writable.
+fn harvest_withheld_fees<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID || accounts.is_empty() {
+        return Ok(());
+    }
+    let has_transfer_fee = {
+        let data = mint_info.try_borrow_data()?;
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?
+            .get_extension_types()?
+            .contains(&ExtensionType::TransferFeeConfig)
+    };
+    if !has_transfer_fee {
+        return Ok(());
+    }
+    require!(mint_info.is_writable, PredictionMarketError::InvalidMint);
+
+    let sources: Vec<&Pubkey> = accounts.iter().map(|account| account.key).collect();
+    let harvest_ix = spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint(
+        &token_program.key(),
+        &mint.key(),
+        &sources,
+    )?;
+    let mut infos = vec![mint_info, token_program.to_account_info()];
+    infos.extend_from_slice(accounts);
+    anchor_lang::solana_program::program::invoke(&harvest_ix, &infos)?;
+    Ok(())
+}
+
+// ===== COLLATERAL ALLOWLIST =====
+#[derive(Accounts)]
+pub struct SetCollateralConfig<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = CollateralConfig::SIZE,
+        seeds = [b"collateral_config", collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub collateral_config: Account<'info, CollateralConfig>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        has_one = admin @ PredictionMarketError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Adds or updates a collateral mint on the allowlist. Existing markets keep
+/// the limits they were created with.
+pub fn set_collateral_config(ctx: Context<SetCollateralConfig>, params: CollateralConfigParams) -> Result<()> {
+    let decimals = ctx.accounts.collateral_mint.decimals;
+    params.validate(decimals)?;
+    validate_collateral_mint(&ctx.accounts.collateral_mint)?;
+
+    let collateral_config = &mut ctx.accounts.collateral_config;
+    collateral_config.mint = ctx.accounts.collateral_mint.key();
+    collateral_config.decimals = decimals;
+    collateral_config.min_bet_amount = params.min_bet_amount;
+    collateral_config.max_bet_amount = params.max_bet_amount;
+    collateral_config.fee_bps_override = params.fee_bps_override;
+    collateral_config.enabled = params.enabled;
+    collateral_config.creator_bond = params.creator_bond;
+    collateral_config.bump = ctx.bumps.collateral_config;
+
+    msg!(
+        "Collateral {} ({} decimals) enabled: {}, bets {}-{}",
+        collateral_config.mint,
+        decimals,
+        params.enabled,
+        params.min_bet_amount,
+        params.max_bet_amount
+    );
+
+    Ok(())
+}
+
+// ===== INITIALIZE MARKET WITH ENHANCED CONTROLS =====
+#[derive(Accounts)]
+#[instruction(
+    market_nonce: Option<u64>,
+    initial_price: i64,
+    target_change_bps: i64,
+    settle_time: i64,
+    resolver_authority: Option<Pubkey>,
+    config: MarketConfig
+)]
+pub struct InitializeMarket<'info> {
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = CreatorProfile::SIZE,
+        seeds = [b"creator_profile", creator.key().as_ref()],
+        bump
+    )]
+    pub creator_profile: Box<Account<'info, CreatorProfile>>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Market::SIZE,
+        seeds = [b"market", creator.key().as_ref(), &creator_profile.resolve_nonce(market_nonce).to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,  // Changed from fee_collector to market for consistency
+        token::token_program = token_program,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Pays the creator bond. Omit on native SOL markets to post it in lamports.
+    #[account(
+        mut,
+        constraint = creator_token_account.owner == creator.key(),
+        constraint = creator_token_account.mint == collateral_mint.key()
+    )]
+    pub creator_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = FeeCollector::SIZE,
+        seeds = [b"fee_collector"],
+        bump
+    )]
+    pub fee_collector: Account<'info, FeeCollector>,
+
+    #[account(
+        seeds = [b"collateral_config", collateral_mint.key().as_ref()],
+        bump = collateral_config.bump,
+        constraint = collateral_config.enabled @ PredictionMarketError::CollateralNotAllowed
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: This account is the Pyth oracle price feed. It's validated at runtime when reading price data.
+    pub pyth_feed: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"category".as_ref(), &[config.category as u8]],
+        bump
+    )]
+    pub category_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", category_registry.key().as_ref(), &category_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub category_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"feed".as_ref(), pyth_feed.key().as_ref()],
+        bump
+    )]
+    pub feed_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", feed_registry.key().as_ref(), &feed_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub feed_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"creator".as_ref(), creator.key().as_ref()],
+        bump
+    )]
+    pub creator_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", creator_registry.key().as_ref(), &creator_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub creator_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Creates a market. With `market_nonce` left as `None` the nonce comes from
+/// the creator's `CreatorProfile` counter; an explicit nonce is kept for
+/// clients that derive the market address themselves.
+pub fn initialize_market(
+    ctx: Context<InitializeMarket>,
+    market_nonce: Option<u64>,
+    initial_price: i64,
+    target_change_bps: i64,
+    settle_time: i64,
+    resolver_authority: Option<Pubkey>,
+    config: MarketConfig,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let profile = &mut ctx.accounts.creator_profile;
+    if profile.creator == Pubkey::default() {
+        profile.creator = ctx.accounts.creator.key();
+        profile.bump = ctx.bumps.creator_profile;
+    }
+    let market_nonce = match market_nonce {
+        Some(nonce) => {
+            require!(nonce & COUNTER_NONCE_FLAG == 0, PredictionMarketError::InvalidMarketNonce);
+            nonce
+        }
+        None => profile.next_nonce()?,
+    };
+
+    validate_collateral_mint(&ctx.accounts.collateral_mint)?;
+
+    let setup = MarketSetup {
+        creator: ctx.accounts.creator.key(),
+        pyth_feed: ctx.accounts.pyth_feed.key(),
+        collateral_mint: ctx.accounts.collateral_mint.key(),
+        fee_collector: ctx.accounts.fee_collector.key(),
+        resolver_authority: resolver_authority.unwrap_or(ctx.accounts.creator.key()),
+        nonce: market_nonce,
+        bump: ctx.bumps.market,
+        initial_price,
+        target_change_bps,
+        settle_time,
+    };
+    init_market_account(&mut ctx.accounts.market, setup, &config, &ctx.accounts.collateral_config, clock.unix_timestamp)?;
+    let accounts = &mut *ctx.accounts;
+    register_market(
+        &mut accounts.market,
+        [
+            (&mut accounts.category_registry, ctx.bumps.category_registry, RegistryKind::Category, &mut accounts.category_page),
+            (&mut accounts.feed_registry, ctx.bumps.feed_registry, RegistryKind::Feed, &mut accounts.feed_page),
+            (&mut accounts.creator_registry, ctx.bumps.creator_registry, RegistryKind::Creator, &mut accounts.creator_page),
+        ],
+    )?;
+
+    init_fee_collector_if_needed(&mut ctx.accounts.fee_collector, ctx.accounts.creator.key());
+
+    let bond = ctx.accounts.collateral_config.creator_bond;
+    if bond > 0 {
+        ctx.accounts.market.bond_amount = deposit(
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.collateral_mint,
+            ctx.accounts.creator_token_account.as_deref(),
+            &mut ctx.accounts.bond_vault,
+            ctx.accounts.creator.to_account_info(),
+            &[],
+            bond,
+        )?;
+    }
+
+    let market = &ctx.accounts.market;
+    msg!("Market {} initialized with initial price: {}", market_nonce, initial_price);
+    msg!("Target change: {} bps", target_change_bps);
+    msg!("Fee: {} bps, bet limits: {}-{}", market.fee_bps, market.min_bet_amount, market.max_bet_amount);
+    msg!("Betting closes at {}", market.betting_close_time);
+    msg!("Fee collector: {}", ctx.accounts.fee_collector.key());
+    msg!("Market status: {:?}", market.market_status);
+
+    Ok(())
+}
+
+/// Fields that differ between the ways a market can be created.
+struct MarketSetup {
+    creator: Pubkey,
+    pyth_feed: Pubkey,
+    collateral_mint: Pubkey,
+    fee_collector: Pubkey,
+    resolver_authority: Pubkey,
+    nonce: u64,
+    bump: u8,
+    initial_price: i64,
+    target_change_bps: i64,
+    settle_time: i64,
+}
+
+/// Validates `config` and the settlement schedule, then writes a fresh market.
+fn init_market_account(
+    market: &mut Market,
+    setup: MarketSetup,
+    config: &MarketConfig,
+    collateral: &CollateralConfig,
+    now: i64,
+) -> Result<()> {
+    let settle_time = setup.settle_time;
+
+    // Validate settlement time with proper error messages
+    let params = collateral.bounds()?;
+    config.validate(&params)?;
+    config.validate_schedule(now, settle_time)?;
+    Market::validate_target_change(setup.target_change_bps)?;
+    require!(
+        settle_time > now + params.min_settlement_time,
+        PredictionMarketError::SettlementTimeTooSoon  // Use appropriate error
+    );
+    require!(
+        settle_time < now + params.max_settlement_time,
+        PredictionMarketError::SettlementTimeTooFar  // Use appropriate error
+    );
+
+    // Initialize market
+    market.creator = setup.creator;
+    market.pyth_feed = setup.pyth_feed;
+    market.target_price = setup.initial_price; // Store initial price here
+    market.settle_time = settle_time;
+    market.yes_pool = 0;
+    market.no_pool = 0;
+    market.collateral_mint = setup.collateral_mint;
+    market.is_resolved = false;
+    market.winning_outcome = None;
+    market.nonce = setup.nonce;
+    market.bump = setup.bump;
+    market.resolver_authority = setup.resolver_authority;
+    market.total_volume = 0;
+    market.fee_bps = collateral.fee_bps(config.fee_bps);
+    market.final_price = None;
+
+    // Initialize extended fields
+    market.total_fees_collected = 0;
+    market.fee_collector = setup.fee_collector;
+    market.oracle_confidence = config.oracle_confidence;
+    market.min_bet_amount = config.min_bet_amount;
+    market.max_bet_amount = config.max_bet_amount;
+    market.market_status = MarketStatus::PendingLiquidity;
+    market.created_at = now;
+    market.description = config.description_bytes()?;
+    market.category = config.category;
+    market.oracle_last_update = now;
+    market.emergency_paused = false;
+    market.min_liquidity = config.min_liquidity;
+    market.liquidity_locked_until = settle_time;
+    market.oracle_staleness_threshold = config.oracle_staleness_threshold;
+    market.metadata_uri = [0u8; 200];
+    market.metadata_hash = [0u8; 32];
+    market.unclaimed_positions = 0;
+    market.pause_scope = 0;
+    market.funding_deadline = if config.min_liquidity > 0 { config.funding_deadline } else { now };
+    market.betting_close_time = config.betting_close_time(settle_time)?;
+    market.bond_amount = 0;
+    // Nothing to return or slash without a bond
+    market.bond_settled = collateral.creator_bond == 0;
+    market.exit_fee_bps = config.exit_fee_bps;
+    market.max_total_pot = config.max_total_pot;
+    market.max_user_stake = config.max_user_stake;
+    market.tokenized = config.tokenized;
+    market.complete_sets = 0;
+    market.open_positions = 0;
+    market.settled_at = 0;
+    market.registry_pages = [Pubkey::default(); 3];
+    market.order_books = 0;
+    market.order_escrow = 0;
+    market.target_change_bps = setup.target_change_bps;
+
+    // Markets without a liquidity requirement open immediately
+    market.activate_if_funded();
+
+    Ok(())
+}
+
+fn init_fee_collector_if_needed(fee_collector: &mut FeeCollector, creator: Pubkey) {
+    if fee_collector.authority == Pubkey::default() {
+        fee_collector.authority = creator;
+        fee_collector.total_fees_collected = 0;
+        fee_collector.treasury = creator;
+        fee_collector.fee_distribution = FeeDistribution {
+            treasury_bps: 5000,   // 50% to treasury
+            liquidity_bps: 3000,  // 30% to LPs
+            creator_bps: 2000,    // 20% to market creator
+        };
+    }
+}
+
+fn init_user_stats_if_needed(user_stats: &mut UserStats, user: Pubkey, bump: u8) {
+    if user_stats.user == Pubkey::default() {
+        user_stats.user = user;
+        user_stats.open_positions = 0;
+        user_stats.bump = bump;
+        user_stats.bet_count = 0;
+        user_stats.referrer = None;
+    }
+}
+
+// ===== MARKET REGISTRY =====
+type RegistrySlot<'a, 'info> = (
+    &'a mut Box<Account<'info, MarketRegistry>>,
+    u8,
+    RegistryKind,
+    &'a mut Box<Account<'info, MarketRegistryPage>>,
+);
+
+/// Appends a new market to the current page of its category, feed and
+/// creator registries, initializing them on first use, and records those
+/// pages on the market so later status changes must update them.
+fn register_market(market: &mut Account<Market>, registries: [RegistrySlot; 3]) -> Result<()> {
+    let entry = RegistryEntry {
+        market: market.key(),
+        status: market.market_status,
+    };
+    for (i, (registry, bump, kind, page)) in registries.into_iter().enumerate() {
+        push_registry_entry(registry, bump, kind, page, entry)?;
+        market.registry_pages[i] = page.key();
+    }
+
+    msg!("Market {} registered", entry.market);
+    Ok(())
+}
+
+/// Appends `entry` to `registry`'s current page, initializing either on first use.
+fn push_registry_entry(
+    registry: &mut Account<MarketRegistry>,
+    bump: u8,
+    kind: RegistryKind,
+    page: &mut Account<MarketRegistryPage>,
+    entry: RegistryEntry,
+) -> Result<()> {
+    if registry.market_count == 0 {
+        registry.kind = kind;
+        registry.bump = bump;
+    }
+    if page.registry == Pubkey::default() {
+        page.registry = registry.key();
+        page.page_index = registry.current_page();
+    }
+
+    page.push(entry)?;
+    registry.market_count = registry.market_count
+        .checked_add(1)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+    Ok(())
+}
+
+/// The category, feed and creator registry pages a market is listed on, in
+/// that order, as recorded on the market when it was created.
+#[derive(Accounts)]
+pub struct RegistryPages<'info> {
+    #[account(mut)]
+    pub category_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(mut)]
+    pub feed_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(mut)]
+    pub creator_page: Box<Account<'info, MarketRegistryPage>>,
+}
+
+impl RegistryPages<'_> {
+    /// Refreshes `market`'s entry on each of its pages. `None` removes the
+    /// entry, used when the market closes.
+    fn update(&mut self, market: &Account<Market>, status: Option<MarketStatus>) -> Result<()> {
+        let pages = [&mut self.category_page, &mut self.feed_page, &mut self.creator_page];
+        for (page, expected) in pages.into_iter().zip(market.registry_pages) {
+            require_keys_eq!(page.key(), expected, PredictionMarketError::InvalidRegistryPage);
+
+            let found = match status {
+                Some(status) => page.set_status(&market.key(), status),
+                None => page.remove(&market.key()),
+            };
+            require!(found, PredictionMarketError::InvalidRegistryPage);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SyncRegistryEntries<'info> {
+    pub market: Account<'info, Market>,
+
+    pub registry_pages: RegistryPages<'info>,
+}
+
+/// Permissionless: copies the market's current status into its registry pages
+/// (e.g. after a PendingLiquidity market activates).
+pub fn sync_registry_entries(ctx: Context<SyncRegistryEntries>) -> Result<()> {
+    let market = &ctx.accounts.market;
+    ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
+    msg!("Synced registry pages to {:?}", market.market_status);
+    Ok(())
+}
+
+// ===== MARKET TEMPLATES =====
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct CreateMarketTemplate<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = MarketTemplate::SIZE,
+        seeds = [b"market_template".as_ref(), &template_id.to_le_bytes()],
+        bump
+    )]
+    pub template: Account<'info, MarketTemplate>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        has_one = admin @ PredictionMarketError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [b"collateral_config", collateral_mint.key().as_ref()],
+        bump = collateral_config.bump,
+        constraint = collateral_config.enabled @ PredictionMarketError::CollateralNotAllowed
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Pyth price feed every market from this template will use; read at instantiation.
+    pub pyth_feed: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_market_template(
+    ctx: Context<CreateMarketTemplate>,
+    template_id: u64,
+    resolver_authority: Pubkey,
+    params: MarketTemplateParams,
+) -> Result<()> {
+    params.validate(&ctx.accounts.collateral_config.bounds()?)?;
+
+    let template = &mut ctx.accounts.template;
+    template.template_id = template_id;
+    template.admin = ctx.accounts.admin.key();
+    template.pyth_feed = ctx.accounts.pyth_feed.key();
+    template.collateral_mint = ctx.accounts.collateral_mint.key();
+    template.resolver_authority = resolver_authority;
+    template.duration = params.duration;
+    template.fee_bps = params.fee_bps;
+    template.min_bet_amount = params.min_bet_amount;
+    template.max_bet_amount = params.max_bet_amount;
+    template.betting_cutoff_secs = params.betting_cutoff_secs;
+    template.category = params.category;
+    template.target_change_bps = params.target_change_bps;
+    template.is_active = true;
+    template.markets_created = 0;
+    template.bump = ctx.bumps.template;
+
+    msg!(
+        "Market template {} registered: feed {}, duration {}s",
+        template_id,
+        template.pyth_feed,
+        params.duration
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetMarketTemplateActive<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_template".as_ref(), &template.template_id.to_le_bytes()],
+        bump = template.bump
+    )]
+    pub template: Account<'info, MarketTemplate>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        has_one = admin @ PredictionMarketError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+pub fn set_market_template_active(ctx: Context<SetMarketTemplateActive>, is_active: bool) -> Result<()> {
+    ctx.accounts.template.is_active = is_active;
+    msg!("Market template {} active: {}", ctx.accounts.template.template_id, is_active);
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(market_nonce: u64)]
+pub struct InstantiateFromTemplate<'info> {
+    #[account(
+        mut,
+        seeds = [b"market_template".as_ref(), &template.template_id.to_le_bytes()],
+        bump = template.bump,
+        constraint = template.is_active @ PredictionMarketError::TemplateDisabled
+    )]
+    pub template: Account<'info, MarketTemplate>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = Market::SIZE,
+        seeds = [b"market", creator.key().as_ref(), &market_nonce.to_le_bytes()],
+        bump
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Pays the creator bond. Omit on native SOL markets to post it in lamports.
+    #[account(
+        mut,
+        constraint = creator_token_account.owner == creator.key(),
+        constraint = creator_token_account.mint == collateral_mint.key()
+    )]
+    pub creator_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = FeeCollector::SIZE,
+        seeds = [b"fee_collector"],
+        bump
+    )]
+    pub fee_collector: Account<'info, FeeCollector>,
+
+    #[account(
+        seeds = [b"collateral_config", collateral_mint.key().as_ref()],
+        bump = collateral_config.bump,
+        constraint = collateral_config.enabled @ PredictionMarketError::CollateralNotAllowed
+    )]
+    pub collateral_config: Box<Account<'info, CollateralConfig>>,
+
+    #[account(address = template.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Must be the template's Pyth feed; the opening price is read from it.
+    #[account(address = template.pyth_feed @ PredictionMarketError::InvalidPythFeed)]
+    pub pyth_feed: AccountInfo<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"category".as_ref(), &[template.category as u8]],
+        bump
+    )]
+    pub category_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", category_registry.key().as_ref(), &category_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub category_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"feed".as_ref(), template.pyth_feed.as_ref()],
+        bump
+    )]
+    pub feed_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", feed_registry.key().as_ref(), &feed_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub feed_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"creator".as_ref(), creator.key().as_ref()],
+        bump
+    )]
+    pub creator_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", creator_registry.key().as_ref(), &creator_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub creator_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn instantiate_from_template(
+    ctx: Context<InstantiateFromTemplate>,
+    market_nonce: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let template = &ctx.accounts.template;
+    require!(market_nonce & COUNTER_NONCE_FLAG == 0, PredictionMarketError::InvalidMarketNonce);
+
+    // The opening price comes from the oracle, never from the caller
+    let (initial_price, _expo, conf) = parse_pyth_price_alternative(&ctx.accounts.pyth_feed)?;
+    let publish_time = parse_pyth_publish_time(&ctx.accounts.pyth_feed)?;
+    require!(initial_price > 0, PredictionMarketError::InvalidOraclePrice);
+
+    let settle_time = clock.unix_timestamp
+        .checked_add(template.duration)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    validate_collateral_mint(&ctx.accounts.collateral_mint)?;
+
+    let setup = MarketSetup {
+        creator: ctx.accounts.creator.key(),
+        pyth_feed: template.pyth_feed,
+        collateral_mint: template.collateral_mint,
+        fee_collector: ctx.accounts.fee_collector.key(),
+        resolver_authority: template.resolver_authority,
+        nonce: market_nonce,
+        bump: ctx.bumps.market,
+        initial_price,
+        target_change_bps: template.target_change_bps,
+        settle_time,
+    };
+    init_market_account(
+        &mut ctx.accounts.market,
+        setup,
+        &template.market_config(),
+        &ctx.accounts.collateral_config,
+        clock.unix_timestamp,
+    )?;
+
+    // A stale or uncertain opening price would fix the wrong target for the market's life
+    ctx.accounts.market.validate_oracle_price(
+        confidence_bps(initial_price, conf),
+        publish_time,
+        clock.unix_timestamp,
+    )?;
+    ctx.accounts.market.oracle_last_update = publish_time;
+
+    let accounts = &mut *ctx.accounts;
+    register_market(
+        &mut accounts.market,
+        [
+            (&mut accounts.category_registry, ctx.bumps.category_registry, RegistryKind::Category, &mut accounts.category_page),
+            (&mut accounts.feed_registry, ctx.bumps.feed_registry, RegistryKind::Feed, &mut accounts.feed_page),
+            (&mut accounts.creator_registry, ctx.bumps.creator_registry, RegistryKind::Creator, &mut accounts.creator_page),
+        ],
+    )?;
+
+    init_fee_collector_if_needed(&mut ctx.accounts.fee_collector, ctx.accounts.creator.key());
+
+    let bond = ctx.accounts.collateral_config.creator_bond;
+    if bond > 0 {
+        ctx.accounts.market.bond_amount = deposit(
+            &ctx.accounts.token_program,
+            &ctx.accounts.system_program,
+            &ctx.accounts.collateral_mint,
+            ctx.accounts.creator_token_account.as_deref(),
+            &mut ctx.accounts.bond_vault,
+            ctx.accounts.creator.to_account_info(),
+            &[],
+            bond,
+        )?;
+    }
+
+    let template = &mut ctx.accounts.template;
+    template.markets_created = template.markets_created
+        .checked_add(1)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    msg!(
+        "Market created from template {} at price {}, settles at {}",
+        template.template_id,
+        initial_price,
+        settle_time
+    );
+
+    Ok(())
+}
+
+// ===== UPDATE MARKET METADATA =====
+#[derive(Accounts)]
+#[instruction(title: String, category: MarketCategory)]
+pub struct UpdateMarketMetadata<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PredictionMarketError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+
+    /// The category page the market is listed on now
+    #[account(
+        mut,
+        address = market.registry_pages[0] @ PredictionMarketError::InvalidRegistryPage
+    )]
+    pub category_page: Box<Account<'info, MarketRegistryPage>>,
+
+    /// Registry of the requested category; the market moves here if it changes
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistry::SIZE,
+        seeds = [b"registry".as_ref(), b"category".as_ref(), &[category as u8]],
+        bump
+    )]
+    pub new_category_registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = MarketRegistryPage::SIZE,
+        seeds = [b"registry_page", new_category_registry.key().as_ref(), &new_category_registry.current_page().to_le_bytes()],
+        bump
+    )]
+    pub new_category_page: Box<Account<'info, MarketRegistryPage>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn update_market_metadata(
+    ctx: Context<UpdateMarketMetadata>,
+    title: String,
+    category: MarketCategory,
+    metadata_uri: String,
+    metadata_hash: [u8; 32],
+) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    let market = &mut accounts.market;
+
+    // Rules are frozen once anyone has money on the market
+    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(market.total_volume == 0, PredictionMarketError::InvalidMarketStatus);
+
+    require!(!title.is_empty(), PredictionMarketError::InvalidMetadata);
+    require!(title.len() <= Market::MAX_TITLE_LEN, PredictionMarketError::InvalidMetadata);
+    require!(
+        metadata_uri.len() <= Market::MAX_METADATA_URI_LEN,
+        PredictionMarketError::InvalidMetadata
+    );
+    // A URI without a hash (or vice versa) can't be verified by clients
+    require!(
+        metadata_uri.is_empty() == (metadata_hash == [0u8; 32]),
+        PredictionMarketError::InvalidMetadata
+    );
+
+    // Move the listing to the new category's registry
+    if category != market.category {
+        require!(
+            accounts.category_page.remove(&market.key()),
+            PredictionMarketError::InvalidRegistryPage
+        );
+        let entry = RegistryEntry {
+            market: market.key(),
+            status: market.market_status,
+        };
+        push_registry_entry(
+            &mut accounts.new_category_registry,
+            ctx.bumps.new_category_registry,
+            RegistryKind::Category,
+            &mut accounts.new_category_page,
+            entry,
+        )?;
+        market.registry_pages[0] = accounts.new_category_page.key();
+    }
+
+    market.description = encode_fixed_str(&title)?;
+    market.category = category;
+    market.metadata_uri = encode_fixed_str(&metadata_uri)?;
+    market.metadata_hash = metadata_hash;
+
+    msg!("Market metadata updated: {}", title);
+    msg!("Category: {:?}, URI: {}", category, metadata_uri);
+
+    Ok(())
+}
+
+// ===== REFERRALS =====
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        init,
+        payer = wallet,
+        space = Referrer::SIZE,
+        seeds = [b"referrer", wallet.key().as_ref()],
+        bump
+    )]
+    pub referrer: Account<'info, Referrer>,
+
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+    let referrer = &mut ctx.accounts.referrer;
+    referrer.wallet = ctx.accounts.wallet.key();
+    referrer.referred_users = 0;
+    referrer.referred_volume = 0;
+    referrer.fees_earned = 0;
+    referrer.bump = ctx.bumps.referrer;
+
+    msg!("Referrer registered: {}", referrer.wallet);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct OpenReferralVault<'info> {
+    #[account(
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump
+    )]
+    pub referrer: Account<'info, Referrer>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = collateral_mint,
+        token::authority = referrer,
+        token::token_program = token_program,
+        seeds = [b"referral_vault", referrer.key().as_ref(), collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub referral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the vault a referrer's fees accrue in for one collateral mint.
+/// Anyone may pay for it, so a bettor can open it before their first bet.
+pub fn open_referral_vault(ctx: Context<OpenReferralVault>) -> Result<()> {
+    msg!(
+        "Opened referral vault for {} in mint {}",
+        ctx.accounts.referrer.wallet,
+        ctx.accounts.collateral_mint.key()
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(
+        seeds = [b"referrer", wallet.key().as_ref()],
+        bump = referrer.bump,
+        has_one = wallet @ PredictionMarketError::Unauthorized
+    )]
+    pub referrer: Account<'info, Referrer>,
+
+    #[account(
+        mut,
+        seeds = [b"referral_vault", referrer.key().as_ref(), collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub referral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = wallet_token_account.owner == wallet.key() @ PredictionMarketError::InvalidOwner,
+        constraint = wallet_token_account.mint == collateral_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub wallet_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    pub wallet: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Pays out everything accrued in the referrer's vault for one collateral mint.
+pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+    let amount = ctx.accounts.referral_vault.amount;
+    require!(amount > 0, PredictionMarketError::InvalidAmount);
+
+    let referrer = &ctx.accounts.referrer;
+    let referrer_seeds: &[&[u8]] = &[b"referrer", referrer.wallet.as_ref(), &[referrer.bump]];
+    let signer = &[referrer_seeds];
+
+    let transfer_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+            from: ctx.accounts.referral_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: ctx.accounts.wallet_token_account.to_account_info(),
+            authority: referrer.to_account_info(),
+        },
+        signer,
+    );
+    token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.collateral_mint.decimals)?;
+
+    emit!(ReferralFeesClaimed {
+        referrer: referrer.wallet,
+        mint: ctx.accounts.collateral_mint.key(),
+        amount,
+    });
+    msg!("Claimed {} tokens of referral fees", amount);
+
+    Ok(())
+}
+
+// ===== BETTING DELEGATES =====
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct ApproveBettingDelegate<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = BettingDelegate::SIZE,
+        seeds = [b"betting_delegate", user.key().as_ref(), delegate.as_ref()],
+        bump
+    )]
+    pub betting_delegate: Account<'info, BettingDelegate>,
+
+    /// Account the delegate bets from; approved to the delegate PDA for `total_cap`
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ PredictionMarketError::InvalidOwner
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Authorizes `delegate` to bet up to `total_cap` in total and `per_bet_cap`
+/// per bet from `user_token_account` until `expires_at`. Approving again
+/// replaces the limits and resets the amount spent. A token account has a
+/// single SPL delegate, so this also replaces any earlier approval on it.
+pub fn approve_betting_delegate(
+    ctx: Context<ApproveBettingDelegate>,
+    delegate: Pubkey,
+    total_cap: u64,
+    per_bet_cap: u64,
+    expires_at: i64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    require!(
+        per_bet_cap > 0 && per_bet_cap <= total_cap,
+        PredictionMarketError::InvalidParameter
+    );
+    require!(expires_at > clock.unix_timestamp, PredictionMarketError::InvalidParameter);
+
+    let betting_delegate = &mut ctx.accounts.betting_delegate;
+    betting_delegate.user = ctx.accounts.user.key();
+    betting_delegate.delegate = delegate;
+    betting_delegate.total_cap = total_cap;
+    betting_delegate.per_bet_cap = per_bet_cap;
+    betting_delegate.spent = 0;
+    betting_delegate.expires_at = expires_at;
+    betting_delegate.bump = ctx.bumps.betting_delegate;
+
+    let approve_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Approve {
+            to: ctx.accounts.user_token_account.to_account_info(),
+            delegate: betting_delegate.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        },
+    );
+    token_interface::approve(approve_ctx, total_cap)?;
+
+    emit!(DelegateApproved {
+        user: betting_delegate.user,
+        delegate,
+        total_cap,
+        per_bet_cap,
+        expires_at,
+    });
+    msg!("Approved betting delegate {} for up to {} tokens", delegate, total_cap);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct RevokeBettingDelegate<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"betting_delegate", user.key().as_ref(), betting_delegate.delegate.as_ref()],
+        bump = betting_delegate.bump,
+        has_one = user @ PredictionMarketError::Unauthorized
+    )]
+    pub betting_delegate: Account<'info, BettingDelegate>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key() @ PredictionMarketError::InvalidOwner
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Closes the delegate account and, if it still holds the SPL approval on
+/// `user_token_account`, revokes that too.
+pub fn revoke_betting_delegate(ctx: Context<RevokeBettingDelegate>) -> Result<()> {
+    let betting_delegate = &ctx.accounts.betting_delegate;
+
+    if Option::<Pubkey>::from(ctx.accounts.user_token_account.delegate) == Some(betting_delegate.key()) {
+        let revoke_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Revoke {
+                source: ctx.accounts.user_token_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token_interface::revoke(revoke_ctx)?;
+    }
+
+    emit!(DelegateRevoked {
+        user: betting_delegate.user,
+        delegate: betting_delegate.delegate,
+    });
+    msg!("Revoked betting delegate {}", betting_delegate.delegate);
+
+    Ok(())
+}
+
+// ===== PLACE BET WITH FEE HANDLING =====
+#[derive(Accounts)]
+pub struct Bet<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Position::SIZE,
+        seeds = [b"position", market.key().as_ref(), better.key().as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Omit on native SOL markets to bet with lamports from `authority`.
+    /// Required when betting through a delegate.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == better.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", better.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    /// Referrer named on the user's first bet, which fixes attribution. Later
+    /// bets may omit it and the referrer on record still gets its share,
+    /// escrowed in the fee vault until `release_referral_fees`.
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+
+    /// Set when `authority` is a session key betting on `better`'s behalf.
+    #[account(
+        mut,
+        seeds = [b"betting_delegate", better.key().as_ref(), authority.key().as_ref()],
+        bump = betting_delegate.bump
+    )]
+    pub betting_delegate: Option<Account<'info, BettingDelegate>>,
+
+    /// CHECK: owner of the position; must be `authority` unless `betting_delegate` is set
+    pub better: UncheckedAccount<'info>,
+
+    /// The user, or their delegate; pays for any accounts created
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// `min_expected_payout` rejects the bet if its payout (stake after fees, at
+/// the pools it lands in) would be lower; `expires_at` rejects it after that time.
+pub fn place_bet(
+    ctx: Context<Bet>,
+    amount: u64,
+    outcome: Outcome,
+    min_expected_payout: Option<u64>,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    check_expiry(expires_at, clock.unix_timestamp)?;
+
+    let accounts = &mut *ctx.accounts;
+    let better = accounts.better.key();
+    let authority_key = accounts.authority.key();
+    init_user_stats_if_needed(&mut accounts.user_stats, better, ctx.bumps.user_stats);
+
+    // A session key spends from the user's token account, where the delegate
+    // PDA holds the SPL approval, so the PDA signs the transfers
+    let delegate_bump = match accounts.betting_delegate.as_mut() {
+        Some(betting_delegate) => {
+            betting_delegate.record_spend(amount, clock.unix_timestamp)?;
+            require!(
+                accounts.user_token_account.is_some(),
+                PredictionMarketError::MissingTokenAccount
+            );
+            betting_delegate.bump
+        }
+        None => {
+            require_keys_eq!(authority_key, better, PredictionMarketError::Unauthorized);
+            0
+        }
+    };
+    let bump_bytes = [delegate_bump];
+    let delegate_seeds: &[&[u8]] = &[
+        b"betting_delegate",
+        better.as_ref(),
+        authority_key.as_ref(),
+        &bump_bytes,
+    ];
+    let delegate_signer = [delegate_seeds];
+    let (authority, authority_seeds): (AccountInfo, &[&[&[u8]]]) = match &accounts.betting_delegate {
+        Some(betting_delegate) => (betting_delegate.to_account_info(), &delegate_signer),
+        None => (accounts.authority.to_account_info(), &[]),
+    };
+
+    execute_bet(
+        BetAccounts {
+            market: &mut accounts.market,
+            target: BetTarget::Position(&mut accounts.position),
+            yes_vault: &mut accounts.yes_vault,
+            no_vault: &mut accounts.no_vault,
+            fee_vault: &mut accounts.fee_vault,
+            user_token_account: accounts.user_token_account.as_ref(),
+            user_stats: &mut accounts.user_stats,
+            referral: ReferralAccounts::escrowed(&mut accounts.referrer),
+            protocol_config: &accounts.protocol_config,
+            collateral_mint: &accounts.collateral_mint,
+            better,
+            authority,
+            authority_seeds,
+            token_program: &accounts.token_program,
+            system_program: &accounts.system_program,
+        },
+        BetOrder { outcome, amount, min_expected_payout },
+        clock.unix_timestamp,
+    )?;
+
+    Ok(())
+}
+
+/// Referrer and referral vault passed with a bet.
+struct ReferralAccounts<'a, 'info> {
+    referrer: Option<&'a mut Referrer>,
+    vault: Option<&'a mut InterfaceAccount<'info, TokenAccount>>,
+}
+
+impl<'a, 'info> ReferralAccounts<'a, 'info> {
+    /// Position bets escrow the referral share, so they pass no vault.
+    fn escrowed(referrer: &'a mut Option<Box<Account<'info, Referrer>>>) -> Self {
+        Self {
+            referrer: referrer.as_deref_mut().map(|referrer| &mut **referrer),
+            vault: None,
+        }
+    }
+}
+
+/// Checks the optional referral vault is the referrer's vault for `mint`; a
+/// vault needs its referrer.
+fn referral_accounts<'a, 'info>(
+    referrer: &'a mut Option<Box<Account<'info, Referrer>>>,
+    referral_vault: &'a mut Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    mint: &Pubkey,
+) -> Result<ReferralAccounts<'a, 'info>> {
+    match (referrer.as_deref_mut(), referral_vault.as_deref_mut()) {
+        (Some(referrer), Some(referral_vault)) => {
+            let (expected, _) = Pubkey::find_program_address(
+                &[b"referral_vault", referrer.key().as_ref(), mint.as_ref()],
+                &crate::ID,
+            );
+            require_keys_eq!(referral_vault.key(), expected, PredictionMarketError::InvalidReferrer);
+            Ok(ReferralAccounts { referrer: Some(&mut **referrer), vault: Some(referral_vault) })
+        }
+        (referrer, None) => Ok(ReferralAccounts { referrer: referrer.map(|referrer| &mut **referrer), vault: None }),
+        (None, Some(_)) => err!(PredictionMarketError::InvalidReferrer),
+    }
+}
+
+fn check_expiry(expires_at: Option<i64>, now: i64) -> Result<()> {
+    if let Some(expires_at) = expires_at {
+        require!(now <= expires_at, PredictionMarketError::TransactionExpired);
+    }
+    Ok(())
+}
+
+/// Accounts a single bet touches; shared by `place_bet` and each leg of `place_bets`.
+struct BetAccounts<'a, 'info> {
+    market: &'a mut Account<'info, Market>,
+    target: BetTarget<'a, 'info>,
+    yes_vault: &'a mut InterfaceAccount<'info, TokenAccount>,
+    no_vault: &'a mut InterfaceAccount<'info, TokenAccount>,
+    fee_vault: &'a mut InterfaceAccount<'info, TokenAccount>,
+    user_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    user_stats: &'a mut UserStats,
+    referral: ReferralAccounts<'a, 'info>,
+    protocol_config: &'a ProtocolConfig,
+    collateral_mint: &'a InterfaceAccount<'info, Mint>,
+    /// Owner of the position
+    better: Pubkey,
+    /// Signs the stake transfers: the better, or a delegate PDA with `authority_seeds`
+    authority: AccountInfo<'info>,
+    authority_seeds: &'a [&'a [&'a [u8]]],
+    token_program: &'a Interface<'info, TokenInterface>,
+    system_program: &'a Program<'info, System>,
+}
+
+/// Where a bet's stake is recorded.
+enum BetTarget<'a, 'info> {
+    Position(&'a mut Account<'info, Position>),
+    /// Tokenized markets mint outcome tokens instead. Tokens move freely, so
+    /// these markets carry no per-user stake cap.
+    Tokens,
+}
+
+/// Runs one bet and returns the stake that landed in the pool.
+fn execute_bet(accounts: BetAccounts, order: BetOrder, now: i64) -> Result<u64> {
+    let BetAccounts {
+        market,
+        target,
+        yes_vault,
+        no_vault,
+        fee_vault,
+        user_token_account,
+        user_stats,
+        referral,
+        protocol_config,
+        collateral_mint,
+        better,
+        authority,
+        authority_seeds,
+        token_program,
+        system_program,
+    } = accounts;
+    let BetOrder { outcome, amount, .. } = order;
+
+    // Validate market state
+    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(
+        market.is_betting_open(now),
+        PredictionMarketError::MarketClosed
+    );
+    protocol_config.require_not_paused(market, PauseScope::BETS)?;
+    require!(market.is_active(), PredictionMarketError::MarketNotActive);
+    match target {
+        BetTarget::Position(_) => require!(!market.tokenized, PredictionMarketError::TokenizedMarket),
+        BetTarget::Tokens => require!(market.tokenized, PredictionMarketError::MarketNotTokenized),
+    }
+
+    // Validate bet amount
+    require!(amount > 0, PredictionMarketError::InvalidAmount);
+    require!(
+        amount >= market.min_bet_amount,
+        PredictionMarketError::BetTooSmall
+    );
+    require!(
+        amount <= market.max_bet_amount,
+        PredictionMarketError::BetTooLarge
+    );
+
+    // Calculate fee
+    let (fee, amount_after_fee) = calculate_fee(amount, market.fee_bps)?;
+
+    // Risk limits, checked on the stake before any transfer fee
+    market.check_capacity(amount_after_fee)?;
+    if let BetTarget::Position(position) = &target {
+        market.check_user_stake(position.get_total_stake(), amount_after_fee)?;
+    }
+
+    // Attribution is fixed by the user's first bet; later bets use the referrer on record
+    let first_bet = user_stats.bet_count == 0;
+    let ReferralAccounts { referrer, vault: referral_vault } = referral;
+    let attributed = user_stats.attribute_referrer(referrer.as_ref().map(|referrer| referrer.wallet))?;
+    // Positions escrow the referral share in the fee vault so a cancelled
+    // market can refund it. Outcome tokens pay it straight to the referral
+    // vault, and without one it stays with the protocol.
+    let escrow_referral = matches!(target, BetTarget::Position(_));
+    let (referral_fee, protocol_fee) = match attributed {
+        Some(_) if escrow_referral || referral_vault.is_some() => protocol_config.split_referral_fee(fee)?,
+        _ => (0, fee),
+    };
+
+    // Transfer fee to fee vault. Pools, fees and volume are credited with
+    // what landed, which is less than requested for mints with a transfer fee.
+    let mut landed = LandedBet::default();
+    if protocol_fee > 0 {
+        landed.protocol_fee = deposit(
+            token_program,
+            system_program,
+            collateral_mint,
+            user_token_account,
+            fee_vault,
+            authority.clone(),
+            authority_seeds,
+            protocol_fee,
+        )?;
+    }
+    market.total_fees_collected = market.total_fees_collected
+        .checked_add(landed.protocol_fee)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    if referral_fee > 0 {
+        let vault = match referral_vault {
+            Some(referral_vault) if !escrow_referral => referral_vault,
+            _ => &mut *fee_vault,
+        };
+        landed.referral_fee = deposit(
+            token_program,
+            system_program,
+            collateral_mint,
+            user_token_account,
+            vault,
+            authority.clone(),
+            authority_seeds,
+            referral_fee,
+        )?;
+    }
+    if escrow_referral {
+        market.referral_escrow = market.referral_escrow
+            .checked_add(landed.referral_fee)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+    }
+
+    // Transfer bet amount to appropriate vault
+    let vault = match outcome {
+        Outcome::Yes => yes_vault,
+        Outcome::No => no_vault,
+    };
+    landed.stake = deposit(
+        token_program,
+        system_program,
+        collateral_mint,
+        user_token_account,
+        vault,
+        authority,
+        authority_seeds,
+        amount_after_fee,
+    )?;
+    let stake = landed.stake;
+    let volume = landed.total()?;
+
+    if let Some(referrer) = referrer {
+        let paid = if escrow_referral { 0 } else { landed.referral_fee };
+        referrer.record_bet(volume, paid)?;
+        if first_bet {
+            referrer.referred_users = referrer.referred_users
+                .checked_add(1)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+    }
+
+    // Priced on what actually landed, before it joins the pool
+    market.check_min_payout(stake, outcome, order.min_expected_payout)?;
+
+    // Store current odds before updating pools
+    let (yes_odds, no_odds) = market.calculate_odds();
+
+    // Update market pools
+    match outcome {
+        Outcome::Yes => {
+            market.yes_pool = market.yes_pool
+                .checked_add(stake)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+        Outcome::No => {
+            market.no_pool = market.no_pool
+                .checked_add(stake)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+        }
+    }
 
     // Update market volume
     market.total_volume = market.total_volume
-        .checked_add(amount)
+        .checked_add(volume)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    msg!(
+        "Bet placed: {} tokens on {:?}, Fee: {} tokens",
+        stake,
+        outcome,
+        fee
+    );
+    msg!("New odds - YES: {:.2}%, NO: {:.2}%",
+        market.yes_pool as f64 / market.get_total_pot() as f64 * 100.0,
+        market.no_pool as f64 / market.get_total_pot() as f64 * 100.0
+    );
+
+    let BetTarget::Position(position) = target else {
+        return Ok(stake);
+    };
+
+    // Update position
+    if position.market == Pubkey::default() {
+        position.market = market.key();
+        position.better = better;
+        position.claimed = false;
+        position.yes_amount = 0;
+        position.no_amount = 0;
+        position.total_invested = 0;
+        position.pending_payout = 0;
+        position.fees_paid = 0;
+        position.referral_fees = 0;
+
+        market.track_position()?;
+    }
+    // A position holds one of the user's slots while it has stake
+    if !position.has_position() {
+        user_stats.open_position(protocol_config.max_open_positions)?;
+    }
+
+    position.add_stake(outcome, stake, landed.stake_share()?)?;
+    position.referral_fees = position.referral_fees
+        .checked_add(landed.referral_fee)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    position.entry_odds_yes = (yes_odds * 10000.0) as u64;
+    position.entry_odds_no = (no_odds * 10000.0) as u64;
+    position.bet_timestamp = now;
+
+    Ok(stake)
+}
+
+// ===== BATCH BETS =====
+#[derive(Accounts)]
+pub struct PlaceBets<'info> {
+    /// Pays for every leg; omit when the markets use native SOL to bet with lamports.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == better.key(),
+        constraint = user_token_account.mint == collateral_mint.key()
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = better,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", better.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// Collateral every market in the batch must use.
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    /// Referrer named on the user's first bet, which fixes attribution. Later
+    /// bets may omit it and the referrer on record still gets its share,
+    /// escrowed in the fee vault until `release_referral_fees`.
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+
+    #[account(mut)]
+    pub better: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Places every bet in `bets` or none of them. Remaining accounts hold, per
+/// bet and in order: market, position, YES vault, NO vault, fee vault (all
+/// writable). Positions are created on first use.
+pub fn place_bets<'info>(
+    ctx: Context<'_, '_, 'info, 'info, PlaceBets<'info>>,
+    bets: Vec<BetOrder>,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    BetOrder::validate_batch(&bets, ctx.remaining_accounts.len())?;
+
+    let clock = Clock::get()?;
+    check_expiry(expires_at, clock.unix_timestamp)?;
+
+    let accounts = &mut *ctx.accounts;
+    init_user_stats_if_needed(&mut accounts.user_stats, accounts.better.key(), ctx.bumps.user_stats);
+
+    let legs = ctx.remaining_accounts.chunks(BetOrder::ACCOUNTS_PER_BET);
+    for (order, leg) in bets.iter().zip(legs) {
+        let [market_info, position_info, yes_info, no_info, fee_info] = leg else {
+            return err!(PredictionMarketError::InvalidBatchAccount);
+        };
+        require!(
+            leg.iter().all(|info| info.is_writable),
+            PredictionMarketError::InvalidBatchAccount
+        );
+
+        let mut market: Account<'info, Market> = Account::try_from(market_info)?;
+        require_keys_eq!(
+            market.collateral_mint,
+            accounts.collateral_mint.key(),
+            PredictionMarketError::InvalidMint
+        );
+        let market_key = market.key();
+
+        let mut position = load_or_create_position(
+            position_info,
+            &market_key,
+            &accounts.better,
+            &accounts.system_program,
+        )?;
+        let mut yes_vault = load_market_vault(yes_info, b"yes_vault", &market_key)?;
+        let mut no_vault = load_market_vault(no_info, b"no_vault", &market_key)?;
+        let mut fee_vault = load_market_vault(fee_info, b"fee_vault", &market_key)?;
+
+        execute_bet(
+            BetAccounts {
+                market: &mut market,
+                target: BetTarget::Position(&mut position),
+                yes_vault: &mut yes_vault,
+                no_vault: &mut no_vault,
+                fee_vault: &mut fee_vault,
+                user_token_account: accounts.user_token_account.as_ref(),
+                user_stats: &mut accounts.user_stats,
+                referral: ReferralAccounts::escrowed(&mut accounts.referrer),
+                protocol_config: &accounts.protocol_config,
+                collateral_mint: &accounts.collateral_mint,
+                better: accounts.better.key(),
+                authority: accounts.better.to_account_info(),
+                authority_seeds: &[],
+                token_program: &accounts.token_program,
+                system_program: &accounts.system_program,
+            },
+            *order,
+            clock.unix_timestamp,
+        )?;
+
+        // Persist now so a later leg on the same market sees this one
+        market.exit(&crate::ID)?;
+        position.exit(&crate::ID)?;
+    }
+
+    msg!("Placed {} bets", bets.len());
+
+    Ok(())
+}
+
+/// Loads the better's position PDA for `market`, creating it if it does not exist yet.
+fn load_or_create_position<'info>(
+    info: &'info AccountInfo<'info>,
+    market: &Pubkey,
+    better: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<Account<'info, Position>> {
+    let better_key = better.key();
+    let (expected, bump) = Pubkey::find_program_address(
+        &[b"position", market.as_ref(), better_key.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(info.key(), expected, PredictionMarketError::InvalidBatchAccount);
+
+    if info.owner == &system_program::ID && info.data_is_empty() {
+        let position_seeds: &[&[u8]] = &[b"position", market.as_ref(), better_key.as_ref(), &[bump]];
+        let signer = &[position_seeds];
+
+        // Allocate and assign rather than create_account, which fails if
+        // someone has already sent lamports to the address
+        let rent = Rent::get()?.minimum_balance(Position::SIZE);
+        let top_up = rent.saturating_sub(info.lamports());
+        if top_up > 0 {
+            let transfer_ctx = CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: better.to_account_info(),
+                    to: info.clone(),
+                },
+            );
+            system_program::transfer(transfer_ctx, top_up)?;
+        }
+        let allocate_ctx = CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Allocate {
+                account_to_allocate: info.clone(),
+            },
+            signer,
+        );
+        system_program::allocate(allocate_ctx, Position::SIZE as u64)?;
+        let assign_ctx = CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::Assign {
+                account_to_assign: info.clone(),
+            },
+            signer,
+        );
+        system_program::assign(assign_ctx, &crate::ID)?;
+
+        // Blank position; `execute_bet` fills it in like `init_if_needed` would
+        let blank = Position {
+            market: Pubkey::default(),
+            better: Pubkey::default(),
+            yes_amount: 0,
+            no_amount: 0,
+            claimed: false,
+            entry_odds_yes: 0,
+            entry_odds_no: 0,
+            bet_timestamp: 0,
+            total_invested: 0,
+            pending_payout: 0,
+            fees_paid: 0,
+            referral_fees: 0,
+        };
+        blank.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+    }
+
+    let position: Account<'info, Position> = Account::try_from(info)?;
+    require!(
+        position.better == Pubkey::default() || position.better == better_key,
+        PredictionMarketError::InvalidBetter
+    );
+    Ok(position)
+}
+
+/// Loads one of `market`'s vaults after checking it is the PDA for `seed`.
+fn load_market_vault<'info>(
+    info: &'info AccountInfo<'info>,
+    seed: &[u8],
+    market: &Pubkey,
+) -> Result<InterfaceAccount<'info, TokenAccount>> {
+    let (expected, _) = Pubkey::find_program_address(&[seed, market.as_ref()], &crate::ID);
+    require_keys_eq!(info.key(), expected, PredictionMarketError::InvalidBatchAccount);
+    InterfaceAccount::try_from(info)
+}
+
+// ===== OUTCOME TOKENS =====
+#[derive(Accounts)]
+pub struct InitializeOutcomeMints<'info> {
+    #[account(
+        has_one = creator @ PredictionMarketError::Unauthorized,
+        constraint = market.tokenized @ PredictionMarketError::MarketNotTokenized
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = creator,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market,
+        mint::token_program = token_program,
+        seeds = [b"yes_mint", market.key().as_ref()],
+        bump
+    )]
+    pub yes_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market,
+        mint::token_program = token_program,
+        seeds = [b"no_mint", market.key().as_ref()],
+        bump
+    )]
+    pub no_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates the YES and NO mints of a tokenized market, with the market PDA
+/// as mint authority and the collateral's decimals.
+pub fn initialize_outcome_mints(ctx: Context<InitializeOutcomeMints>) -> Result<()> {
+    msg!(
+        "Outcome mints created - YES: {}, NO: {}",
+        ctx.accounts.yes_mint.key(),
+        ctx.accounts.no_mint.key()
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, outcome: Outcome)]
+pub struct BuyOutcomeTokens<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [outcome.mint_seed(), market.key().as_ref()],
+        bump
+    )]
+    pub outcome_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Receives the minted outcome tokens.
+    #[account(
+        mut,
+        constraint = outcome_token_account.owner == better.key() @ PredictionMarketError::InvalidOwner,
+        constraint = outcome_token_account.mint == outcome_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub outcome_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Omit on native SOL markets to bet with lamports from `better`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == better.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = better,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", better.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Box<Account<'info, UserStats>>,
+
+    /// Referrer credited with a share of the fee, as in `place_bet`.
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump
+    )]
+    pub referrer: Option<Box<Account<'info, Referrer>>>,
+
+    /// The referrer's `["referral_vault", referrer, mint]` account for this
+    /// collateral, paid the referral share directly; without it the share
+    /// stays with the protocol.
+    #[account(mut)]
+    pub referral_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub better: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Bets on a tokenized market: the stake joins the pool like `place_bet`,
+/// and the better receives one outcome token per unit of stake that landed.
+pub fn buy_outcome_tokens(
+    ctx: Context<BuyOutcomeTokens>,
+    amount: u64,
+    outcome: Outcome,
+    min_expected_payout: Option<u64>,
+    expires_at: Option<i64>,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    check_expiry(expires_at, clock.unix_timestamp)?;
+
+    let accounts = &mut *ctx.accounts;
+    init_user_stats_if_needed(&mut accounts.user_stats, accounts.better.key(), ctx.bumps.user_stats);
+
+    let stake = execute_bet(
+        BetAccounts {
+            market: &mut accounts.market,
+            target: BetTarget::Tokens,
+            yes_vault: &mut accounts.yes_vault,
+            no_vault: &mut accounts.no_vault,
+            fee_vault: &mut accounts.fee_vault,
+            user_token_account: accounts.user_token_account.as_deref(),
+            user_stats: &mut accounts.user_stats,
+            referral: referral_accounts(
+                &mut accounts.referrer,
+                &mut accounts.referral_vault,
+                &accounts.collateral_mint.key(),
+            )?,
+            protocol_config: &accounts.protocol_config,
+            collateral_mint: &accounts.collateral_mint,
+            better: accounts.better.key(),
+            authority: accounts.better.to_account_info(),
+            authority_seeds: &[],
+            token_program: &accounts.token_program,
+            system_program: &accounts.system_program,
+        },
+        BetOrder { outcome, amount, min_expected_payout },
+        clock.unix_timestamp,
+    )?;
+
+    let market = &accounts.market;
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[market_seeds];
+
+    let mint_ctx = CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        MintTo {
+            mint: accounts.outcome_mint.to_account_info(),
+            to: accounts.outcome_token_account.to_account_info(),
+            authority: market.to_account_info(),
+        },
+        signer,
+    );
+    token_interface::mint_to(mint_ctx, stake)?;
+
+    msg!("Minted {} {:?} outcome tokens", stake, outcome);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(outcome: Outcome)]
+pub struct RedeemOutcomeTokens<'info> {
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [outcome.mint_seed(), market.key().as_ref()],
+        bump
+    )]
+    pub outcome_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = outcome_token_account.owner == holder.key() @ PredictionMarketError::InvalidOwner,
+        constraint = outcome_token_account.mint == outcome_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub outcome_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Omit on native SOL markets to be paid in lamports through `unwrap_account`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == holder.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Temporary wrapped-SOL account the payout passes through; closed to `holder`.
+    #[account(
+        init,
+        payer = holder,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"unwrap", market.key().as_ref(), holder.key().as_ref()],
+        bump
+    )]
+    pub unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Burns `amount` outcome tokens for collateral. After resolution winning
+/// tokens pay their stake plus a pro-rata share of the losing pool; on a
+/// cancelled market either side is refunded 1:1 (fees are not returned).
+pub fn redeem_outcome_tokens(ctx: Context<RedeemOutcomeTokens>, outcome: Outcome, amount: u64) -> Result<()> {
+    let market = &ctx.accounts.market;
+
+    require!(market.tokenized, PredictionMarketError::MarketNotTokenized);
+    require!(amount > 0, PredictionMarketError::InvalidAmount);
+    ctx.accounts.protocol_config.require_not_paused(market, PauseScope::CLAIMS)?;
+
+    let (side_vault, other_vault, side_pool, other_pool) = match outcome {
+        Outcome::Yes => (&ctx.accounts.yes_vault, &ctx.accounts.no_vault, market.yes_pool, market.no_pool),
+        Outcome::No => (&ctx.accounts.no_vault, &ctx.accounts.yes_vault, market.no_pool, market.yes_pool),
+    };
+
+    let profit = if market.is_cancelled() {
+        0
+    } else {
+        require!(market.is_resolved, PredictionMarketError::MarketNotResolved);
+        require!(
+            market.winning_outcome == Some(outcome as u8),
+            PredictionMarketError::InvalidOutcome
+        );
+        ((amount as u128)
+            .checked_mul(other_pool as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?
+            .checked_div(side_pool as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?) as u64
+    };
+
+    let burn_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Burn {
+            mint: ctx.accounts.outcome_mint.to_account_info(),
+            from: ctx.accounts.outcome_token_account.to_account_info(),
+            authority: ctx.accounts.holder.to_account_info(),
+        },
+    );
+    token_interface::burn(burn_ctx, amount)?;
+
+    let payout_account = match (&ctx.accounts.user_token_account, &ctx.accounts.unwrap_account) {
+        (Some(user_token_account), _) => user_token_account.to_account_info(),
+        (None, Some(unwrap_account)) if market.is_native() => unwrap_account.to_account_info(),
+        _ => return err!(PredictionMarketError::MissingTokenAccount),
+    };
+
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[market_seeds];
+
+    for (vault, transfer_amount) in [(side_vault, amount), (other_vault, profit)] {
+        if transfer_amount == 0 {
+            continue;
+        }
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: payout_account.clone(),
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, transfer_amount, ctx.accounts.collateral_mint.decimals)?;
+    }
+
+    if let Some(unwrap_account) = &ctx.accounts.unwrap_account {
+        unwrap_to(&ctx.accounts.token_program, unwrap_account, ctx.accounts.holder.to_account_info(), market)?;
+    }
+
+    let payout = amount
+        .checked_add(profit)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    emit!(OutcomeTokensRedeemed {
+        market: market.key(),
+        holder: ctx.accounts.holder.key(),
+        outcome: outcome as u8,
+        amount,
+        payout,
+    });
+    msg!("Redeemed {} {:?} outcome tokens for {} tokens", amount, outcome, payout);
+
+    Ok(())
+}
+
+// ===== COMPLETE SETS =====
+#[derive(Accounts)]
+pub struct InitializeShareMints<'info> {
+    pub market: Account<'info, Market>,
+
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market,
+        mint::token_program = token_program,
+        seeds = [b"yes_share_mint", market.key().as_ref()],
+        bump
+    )]
+    pub yes_share_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = collateral_mint.decimals,
+        mint::authority = market,
+        mint::token_program = token_program,
+        seeds = [b"no_share_mint", market.key().as_ref()],
+        bump
+    )]
+    pub no_share_mint: InterfaceAccount<'info, Mint>,
+
+    /// Holds the collateral behind every outstanding complete set.
+    #[account(
+        init,
+        payer = payer,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"set_vault", market.key().as_ref()],
+        bump
+    )]
+    pub set_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Creates a market's YES/NO share mints and set vault. Anyone may pay for them.
+/// These are separate from a tokenized market's outcome mints: shares pay a
+/// fixed 1:1 from the set vault, outcome tokens a floating share of the pools.
+pub fn initialize_share_mints(ctx: Context<InitializeShareMints>) -> Result<()> {
+    msg!(
+        "Share mints created - YES: {}, NO: {}",
+        ctx.accounts.yes_share_mint.key(),
+        ctx.accounts.no_share_mint.key()
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MintCompleteSet<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_share_mint", market.key().as_ref()],
+        bump
+    )]
+    pub yes_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"no_share_mint", market.key().as_ref()],
+        bump
+    )]
+    pub no_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = yes_share_account.owner == user.key() @ PredictionMarketError::InvalidOwner,
+        constraint = yes_share_account.mint == yes_share_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub yes_share_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = no_share_account.owner == user.key() @ PredictionMarketError::InvalidOwner,
+        constraint = no_share_account.mint == no_share_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub no_share_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"set_vault", market.key().as_ref()],
+        bump
+    )]
+    pub set_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Omit on native SOL markets to deposit lamports from `user`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Deposits `amount` collateral into the set vault and mints that many YES
+/// and NO shares. The parimutuel pools are untouched.
+pub fn mint_complete_set(ctx: Context<MintCompleteSet>, amount: u64) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+
+    require!(amount > 0, PredictionMarketError::InvalidAmount);
+    require!(!accounts.market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(!accounts.market.is_cancelled(), PredictionMarketError::MarketNotActive);
+    accounts.protocol_config.require_not_paused(&accounts.market, PauseScope::LIQUIDITY)?;
+
+    // Sets are backed by what landed after any transfer fee
+    let landed = deposit(
+        &accounts.token_program,
+        &accounts.system_program,
+        &accounts.collateral_mint,
+        accounts.user_token_account.as_deref(),
+        &mut accounts.set_vault,
+        accounts.user.to_account_info(),
+        &[],
+        amount,
+    )?;
+
+    let market = &mut accounts.market;
+    market.complete_sets = market.complete_sets
+        .checked_add(landed)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[market_seeds];
+
+    let mints = [
+        (&accounts.yes_share_mint, &accounts.yes_share_account),
+        (&accounts.no_share_mint, &accounts.no_share_account),
+    ];
+    for (mint, to) in mints {
+        let mint_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            MintTo {
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::mint_to(mint_ctx, landed)?;
+    }
+
+    emit!(CompleteSetsMinted {
+        market: market.key(),
+        user: accounts.user.key(),
+        amount: landed,
+    });
+    msg!("Minted {} complete sets", landed);
+
+    Ok(())
+}
+
+/// Accounts for burning shares back into collateral; shared by
+/// `merge_complete_set`, `redeem_winning_shares` and `redeem_cancelled_shares`.
+#[derive(Accounts)]
+pub struct BurnShares<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_share_mint", market.key().as_ref()],
+        bump
+    )]
+    pub yes_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"no_share_mint", market.key().as_ref()],
+        bump
+    )]
+    pub no_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = yes_share_account.owner == user.key() @ PredictionMarketError::InvalidOwner,
+        constraint = yes_share_account.mint == yes_share_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub yes_share_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = no_share_account.owner == user.key() @ PredictionMarketError::InvalidOwner,
+        constraint = no_share_account.mint == no_share_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub no_share_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"set_vault", market.key().as_ref()],
+        bump
+    )]
+    pub set_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Omit on native SOL markets to be paid in lamports through `unwrap_account`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Temporary wrapped-SOL account the payout passes through; closed to `user`.
+    #[account(
+        init,
+        payer = user,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"unwrap", market.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub unwrap_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> BurnShares<'info> {
+    fn burn(&self, side: Outcome, amount: u64) -> Result<()> {
+        let (mint, from) = match side {
+            Outcome::Yes => (&self.yes_share_mint, &self.yes_share_account),
+            Outcome::No => (&self.no_share_mint, &self.no_share_account),
+        };
+        let burn_ctx = CpiContext::new(
+            self.token_program.to_account_info(),
+            Burn {
+                mint: mint.to_account_info(),
+                from: from.to_account_info(),
+                authority: self.user.to_account_info(),
+            },
+        );
+        token_interface::burn(burn_ctx, amount)
+    }
+
+    /// Pays `amount` collateral out of the set vault and retires that many sets.
+    fn pay_out(&mut self, amount: u64) -> Result<()> {
+        let market = &self.market;
+        let payout_account = match (&self.user_token_account, &self.unwrap_account) {
+            (Some(user_token_account), _) => user_token_account.to_account_info(),
+            (None, Some(unwrap_account)) if market.is_native() => unwrap_account.to_account_info(),
+            _ => return err!(PredictionMarketError::MissingTokenAccount),
+        };
+
+        let nonce_bytes = market.nonce.to_le_bytes();
+        let market_seeds: &[&[u8]] = &[
+            b"market",
+            market.creator.as_ref(),
+            &nonce_bytes,
+            &[market.bump],
+        ];
+        let signer = &[market_seeds];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.set_vault.to_account_info(),
+                mint: self.collateral_mint.to_account_info(),
+                to: payout_account,
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, self.collateral_mint.decimals)?;
+
+        if let Some(unwrap_account) = &self.unwrap_account {
+            unwrap_to(&self.token_program, unwrap_account, self.user.to_account_info(), market)?;
+        }
+
+        self.market.complete_sets = self.market.complete_sets
+            .checked_sub(amount)
+            .ok_or(PredictionMarketError::InsufficientBalance)?;
+        Ok(())
+    }
+}
+
+/// Burns `amount` YES and `amount` NO shares for `amount` collateral, at any time.
+pub fn merge_complete_set(ctx: Context<BurnShares>, amount: u64) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+
+    require!(amount > 0, PredictionMarketError::InvalidAmount);
+    accounts.protocol_config.require_not_paused(&accounts.market, PauseScope::LIQUIDITY)?;
+
+    accounts.burn(Outcome::Yes, amount)?;
+    accounts.burn(Outcome::No, amount)?;
+    accounts.pay_out(amount)?;
+
+    emit!(CompleteSetsMerged {
+        market: accounts.market.key(),
+        user: accounts.user.key(),
+        amount,
+    });
+    msg!("Merged {} complete sets", amount);
+
+    Ok(())
+}
+
+/// After resolution, burns `amount` shares of the winning side for `amount` collateral.
+pub fn redeem_winning_shares(ctx: Context<BurnShares>, amount: u64) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+
+    require!(amount > 0, PredictionMarketError::InvalidAmount);
+    require!(accounts.market.is_resolved, PredictionMarketError::MarketNotResolved);
+    accounts.protocol_config.require_not_paused(&accounts.market, PauseScope::CLAIMS)?;
+
+    let winner = match accounts.market.winning_outcome {
+        Some(0) => Outcome::Yes,
+        Some(1) => Outcome::No,
+        _ => return err!(PredictionMarketError::MarketNotResolved),
+    };
+    accounts.burn(winner, amount)?;
+    accounts.pay_out(amount)?;
+
+    emit!(WinningSharesRedeemed {
+        market: accounts.market.key(),
+        holder: accounts.user.key(),
+        outcome: winner as u8,
+        amount,
+    });
+    msg!("Redeemed {} {:?} shares", amount, winner);
+
+    Ok(())
+}
+
+/// On a cancelled market, burns `amount` shares of either side for half their
+/// face value, rounded down.
+pub fn redeem_cancelled_shares(ctx: Context<BurnShares>, outcome: Outcome, amount: u64) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+
+    require!(accounts.market.is_cancelled(), PredictionMarketError::MarketNotCancelled);
+    accounts.protocol_config.require_not_paused(&accounts.market, PauseScope::CLAIMS)?;
+
+    let value = accounts.market.share_value(outcome, amount)?;
+    require!(value > 0, PredictionMarketError::InvalidAmount);
+    accounts.burn(outcome, amount)?;
+    accounts.pay_out(value)?;
+
+    emit!(CancelledSharesRedeemed {
+        market: accounts.market.key(),
+        holder: accounts.user.key(),
+        outcome: outcome as u8,
+        shares: amount,
+        amount: value,
+    });
+    msg!("Redeemed {} {:?} shares of a cancelled market for {}", amount, outcome, value);
+
+    Ok(())
+}
+
+// ===== ORDER BOOK =====
+#[derive(Accounts)]
+#[instruction(outcome: Outcome)]
+pub struct InitializeOrderBook<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = OrderBook::SIZE,
+        seeds = [b"order_book", market.key().as_ref(), &[outcome as u8]],
+        bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+
+    #[account(
+        seeds = [outcome.share_mint_seed(), market.key().as_ref()],
+        bump
+    )]
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// Holds shares offered by asks and bought by bids until they settle.
+    #[account(
+        init,
+        payer = payer,
+        token::mint = share_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"share_escrow", market.key().as_ref(), &[outcome as u8]],
+        bump
+    )]
+    pub share_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Collateral escrowed by bids and owed to filled asks; shared by both books.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"order_vault", market.key().as_ref()],
+        bump
+    )]
+    pub order_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens the order book for one outcome's shares; needs `initialize_share_mints` first.
+pub fn initialize_order_book(ctx: Context<InitializeOrderBook>, outcome: Outcome) -> Result<()> {
+    let order_book = &mut ctx.accounts.order_book;
+    order_book.market = ctx.accounts.market.key();
+    order_book.outcome = outcome as u8;
+    order_book.next_order_id = 0;
+    order_book.bump = ctx.bumps.order_book;
+    order_book.bids = Vec::new();
+    order_book.asks = Vec::new();
+    ctx.accounts.market.order_books |= 1 << outcome as u8;
+
+    msg!("Order book opened for {:?} shares", outcome);
+    Ok(())
+}
+
+/// Accounts for trading one outcome's shares; shared by `post_order` and `cancel_order`.
+#[derive(Accounts)]
+#[instruction(outcome: Outcome)]
+pub struct TradeShares<'info> {
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref(), &[outcome as u8]],
+        bump = order_book.bump
+    )]
+    pub order_book: Box<Account<'info, OrderBook>>,
+
+    #[account(
+        seeds = [outcome.share_mint_seed(), market.key().as_ref()],
+        bump
+    )]
+    pub share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"share_escrow", market.key().as_ref(), &[outcome as u8]],
+        bump
+    )]
+    pub share_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"order_vault", market.key().as_ref()],
+        bump
+    )]
+    pub order_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = trader_share_account.owner == trader.key() @ PredictionMarketError::InvalidOwner,
+        constraint = trader_share_account.mint == share_mint.key() @ PredictionMarketError::InvalidMint
+    )]
+    pub trader_share_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = trader_token_account.owner == trader.key() @ PredictionMarketError::InvalidOwner,
+        constraint = trader_token_account.mint == market.collateral_mint @ PredictionMarketError::InvalidMint
+    )]
+    pub trader_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Box<Account<'info, ProtocolConfig>>,
+
+    /// Fills, evictions and escrow dust are credited here; created on first use.
+    #[account(
+        init_if_needed,
+        payer = trader,
+        space = TraderBalance::SIZE,
+        seeds = [b"trader_balance", order_book.key().as_ref(), trader.key().as_ref()],
+        bump
+    )]
+    pub trader_balance: Box<Account<'info, TraderBalance>>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub trader: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TradeShares<'info> {
+    fn init_trader_balance_if_needed(&mut self, bump: u8) {
+        let balance = &mut self.trader_balance;
+        if balance.owner == Pubkey::default() {
+            balance.order_book = self.order_book.key();
+            balance.owner = self.trader.key();
+            balance.bump = bump;
+        }
+    }
+
+    /// Pays each credit into its owner's `TraderBalance`. The trader's own
+    /// balance is in the accounts struct; every other maker's must be passed
+    /// as a remaining account.
+    fn credit_makers(&mut self, balances: &[AccountInfo], credits: &[Credit]) -> Result<()> {
+        let order_book = self.order_book.key();
+        for credit in credits {
+            if credit.owner == self.trader.key() {
+                self.trader_balance.add(credit)?;
+                continue;
+            }
+            let (address, _) = Pubkey::find_program_address(
+                &[b"trader_balance", order_book.as_ref(), credit.owner.as_ref()],
+                &crate::ID,
+            );
+            let info = balances
+                .iter()
+                .find(|info| info.key() == address)
+                .ok_or(PredictionMarketError::MissingTraderBalance)?;
+            require_keys_eq!(*info.owner, crate::ID, PredictionMarketError::MissingTraderBalance);
+
+            let mut data = info.try_borrow_mut_data()?;
+            let mut balance = TraderBalance::try_deserialize(&mut &data[..])?;
+            balance.add(credit)?;
+            balance.try_serialize(&mut &mut data[..])?;
+        }
+        Ok(())
+    }
+
+    /// Moves escrowed collateral (`order_vault`) or shares (`share_escrow`) to the trader.
+    fn release(&mut self, side: OrderSide, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        self.market.release_order_funds(amount)?;
+        let (from, mint, to) = match side {
+            OrderSide::Bid => (&self.order_vault, &self.collateral_mint, &self.trader_token_account),
+            OrderSide::Ask => (&self.share_escrow, &self.share_mint, &self.trader_share_account),
+        };
+
+        let market = &self.market;
+        let nonce_bytes = market.nonce.to_le_bytes();
+        let market_seeds: &[&[u8]] = &[
+            b"market",
+            market.creator.as_ref(),
+            &nonce_bytes,
+            &[market.bump],
+        ];
+        let signer = &[market_seeds];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, mint.decimals)
+    }
+}
+
+/// Posts a limit order for `quantity` shares at `price_bps` collateral per
+/// share (10_000 = 1:1). It first fills against crossing orders at their
+/// prices; any remainder worth at least the market's minimum bet rests on the
+/// book with its funds escrowed, and smaller remainders are dropped. Makers
+/// filled or evicted by this order must have their `TraderBalance` accounts
+/// passed as remaining accounts.
+pub fn post_order(
+    ctx: Context<TradeShares>,
+    outcome: Outcome,
+    side: OrderSide,
+    price_bps: u16,
+    quantity: u64,
+) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    let market = &accounts.market;
+    let clock = Clock::get()?;
+
+    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(!market.is_cancelled(), PredictionMarketError::MarketNotActive);
+    require!(market.is_betting_open(clock.unix_timestamp), PredictionMarketError::MarketClosed);
+    accounts.protocol_config.require_not_paused(market, PauseScope::BETS)?;
+    require!(price_bps > 0 && price_bps < 10_000, PredictionMarketError::InvalidParameter);
+    // Minimum notional, so filling the book costs real collateral
+    let min_notional = market.min_bet_amount.max(1);
+    require!(OrderBook::cost(quantity, price_bps)? >= min_notional, PredictionMarketError::BetTooSmall);
+
+    accounts.init_trader_balance_if_needed(ctx.bumps.trader_balance);
+
+    let mut fills = accounts.order_book.match_order(side, price_bps, quantity)?;
+    let unfilled = quantity - fills.filled;
+    let rest = if OrderBook::cost(unfilled, price_bps)? >= min_notional { unfilled } else { 0 };
+
+    let (order_id, evicted) = match side {
+        OrderSide::Bid => {
+            // Pay for the fills and escrow the rest at the limit price
+            let escrow = OrderBook::cost(rest, price_bps)?;
+            let deposit_amount = fills.cost
+                .checked_add(escrow)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+            if deposit_amount > 0 {
+                let landed = transfer_in(
+                    &accounts.token_program,
+                    &accounts.collateral_mint,
+                    &accounts.trader_token_account,
+                    &mut accounts.order_vault,
+                    accounts.trader.to_account_info(),
+                    &[],
+                    deposit_amount,
+                )?;
+                // Escrow is paid out in exact amounts, so fees on transfer cannot be absorbed
+                require!(landed == deposit_amount, PredictionMarketError::UnsupportedMintExtension);
+                accounts.market.escrow_order_funds(deposit_amount)?;
+            }
+            accounts.release(OrderSide::Ask, fills.filled)?;
+            match rest {
+                0 => (None, None),
+                _ => {
+                    let (id, evicted) = accounts.order_book.insert(side, accounts.trader.key(), price_bps, rest, escrow)?;
+                    (Some(id), evicted)
+                }
+            }
+        }
+        OrderSide::Ask => {
+            // Shares sold go to escrow for the bids; resting ones stay there
+            let shares_in = fills.filled
+                .checked_add(rest)
+                .ok_or(PredictionMarketError::MathOverflow)?;
+            transfer_in(
+                &accounts.token_program,
+                &accounts.share_mint,
+                &accounts.trader_share_account,
+                &mut accounts.share_escrow,
+                accounts.trader.to_account_info(),
+                &[],
+                shares_in,
+            )?;
+            accounts.market.escrow_order_funds(shares_in)?;
+            accounts.release(OrderSide::Bid, fills.cost)?;
+            match rest {
+                0 => (None, None),
+                _ => {
+                    let (id, evicted) = accounts.order_book.insert(side, accounts.trader.key(), price_bps, rest, rest)?;
+                    (Some(id), evicted)
+                }
+            }
+        }
+    };
+    if let Some(evicted) = evicted {
+        fills.credit(evicted.escrow_credit(side))?;
+    }
+    accounts.credit_makers(ctx.remaining_accounts, &fills.credits)?;
+
+    emit!(OrderPosted {
+        market: accounts.market.key(),
+        outcome: outcome as u8,
+        order_id,
+        owner: accounts.trader.key(),
+        side: side as u8,
+        price_bps,
+        quantity,
+        filled: fills.filled,
+        cost: fills.cost,
+    });
+    msg!(
+        "{:?} {:?} order: {} shares at {} bps, {} filled for {} tokens, {} resting",
+        outcome,
+        side,
+        quantity,
+        price_bps,
+        fills.filled,
+        fills.cost,
+        rest
+    );
+
+    Ok(())
+}
+
+/// Takes the trader's order off the book and returns its unfilled escrow.
+/// Stays available after resolution.
+pub fn cancel_order(ctx: Context<TradeShares>, outcome: Outcome, order_id: u64) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    accounts.init_trader_balance_if_needed(ctx.bumps.trader_balance);
+    let (side, order) = accounts.order_book.remove(order_id, &accounts.trader.key())?;
+    accounts.release(side, order.escrow)?;
+
+    emit!(OrderCancelled {
+        market: accounts.market.key(),
+        outcome: outcome as u8,
+        order_id,
+        owner: order.owner,
+        refunded: order.escrow,
+    });
+    msg!("Order {} cancelled: {} escrow returned", order_id, order.escrow);
+
+    Ok(())
+}
+
+/// Withdraws the shares and collateral the trader's fills and evicted orders
+/// earned on this book. Stays available after resolution.
+pub fn settle_trades(ctx: Context<TradeShares>, outcome: Outcome) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    accounts.init_trader_balance_if_needed(ctx.bumps.trader_balance);
+
+    let shares = std::mem::take(&mut accounts.trader_balance.shares);
+    let collateral = std::mem::take(&mut accounts.trader_balance.collateral);
+    accounts.release(OrderSide::Ask, shares)?;
+    accounts.release(OrderSide::Bid, collateral)?;
+
+    emit!(TradesSettled {
+        market: accounts.market.key(),
+        outcome: outcome as u8,
+        owner: accounts.trader.key(),
+        shares,
+        collateral,
+    });
+    msg!("Settled {} shares and {} tokens", shares, collateral);
+
+    Ok(())
+}
+
+// ===== SELL POSITION (EARLY EXIT) =====
+#[derive(Accounts)]
+pub struct SellPosition<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"position", market.key().as_ref(), seller.key().as_ref()],
+        bump,
+        constraint = position.better == seller.key(),
+        constraint = !position.claimed @ PredictionMarketError::AlreadyClaimed
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", seller.key().as_ref()],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Omit on native SOL markets to be paid in lamports through `unwrap_account`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == seller.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Temporary wrapped-SOL account the proceeds pass through; closed to `seller`.
+    #[account(
+        init,
+        payer = seller,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"unwrap", market.key().as_ref(), seller.key().as_ref()],
+        bump
+    )]
+    pub unwrap_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Sells `amount` of the seller's `outcome` stake back to the pool before
+/// betting closes, at `Market::get_exit_value` less the market's exit fee.
+/// The rest of the stake, fee and pricing discount, goes to the fee vault.
+/// `min_proceeds` rejects the sale if the seller would receive less.
+pub fn sell_position(
+    ctx: Context<SellPosition>,
+    outcome: Outcome,
+    amount: u64,
+    min_proceeds: Option<u64>,
+) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let position = &mut ctx.accounts.position;
+    let clock = Clock::get()?;
+
+    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(
+        market.is_betting_open(clock.unix_timestamp),
+        PredictionMarketError::MarketClosed
+    );
+    ctx.accounts.protocol_config.require_not_paused(market, PauseScope::BETS)?;
+    require!(market.is_active(), PredictionMarketError::MarketNotActive);
+
+    let value = market.get_exit_value(amount, outcome)?;
+    let (_, proceeds) = calculate_fee(value, market.exit_fee_bps)?;
+    if let Some(min_proceeds) = min_proceeds {
+        require!(proceeds >= min_proceeds, PredictionMarketError::SlippageExceeded);
+    }
+    let exit_fee = amount - proceeds;
+    position.reduce_stake(outcome, amount)?;
+    if !position.has_position() {
+        ctx.accounts.user_stats.close_position();
+    }
+
+    let payout_account = match (&ctx.accounts.user_token_account, &ctx.accounts.unwrap_account) {
+        (Some(user_token_account), _) => user_token_account.to_account_info(),
+        (None, Some(unwrap_account)) if market.is_native() => unwrap_account.to_account_info(),
+        _ => return err!(PredictionMarketError::MissingTokenAccount),
+    };
+
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[market_seeds];
+
+    let vault = match outcome {
+        Outcome::Yes => &ctx.accounts.yes_vault,
+        Outcome::No => &ctx.accounts.no_vault,
+    };
+    if proceeds > 0 {
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: payout_account,
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, proceeds, ctx.accounts.collateral_mint.decimals)?;
+    }
+    // Fees are credited with what landed, as on every other fee path
+    let fee_landed = if exit_fee > 0 {
+        transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.collateral_mint,
+            vault,
+            &mut ctx.accounts.fee_vault,
+            market.to_account_info(),
+            signer,
+            exit_fee,
+        )?
+    } else {
+        0
+    };
+
+    if let Some(unwrap_account) = &ctx.accounts.unwrap_account {
+        unwrap_to(&ctx.accounts.token_program, unwrap_account, ctx.accounts.seller.to_account_info(), market)?;
+    }
+
+    // The whole stake leaves the pool. Volume is cumulative and stays as is.
+    let pool = match outcome {
+        Outcome::Yes => &mut market.yes_pool,
+        Outcome::No => &mut market.no_pool,
+    };
+    *pool = pool.checked_sub(amount).ok_or(PredictionMarketError::MathOverflow)?;
+    market.total_fees_collected = market.total_fees_collected
+        .checked_add(fee_landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
+    position.bet_timestamp = clock.unix_timestamp;
+
+    emit!(PositionSold {
+        market: market.key(),
+        better: ctx.accounts.seller.key(),
+        outcome: outcome as u8,
+        amount,
+        proceeds,
+        exit_fee,
+    });
+    msg!(
+        "Sold {} {:?} stake for {} tokens (exit fee {})",
+        amount,
+        outcome,
+        proceeds,
+        exit_fee
+    );
+
+    Ok(())
+}
+
+// ===== TRANSFER POSITION =====
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"position", market.key().as_ref(), sender.key().as_ref()],
+        bump,
+        constraint = from_position.better == sender.key(),
+        constraint = !from_position.claimed @ PredictionMarketError::AlreadyClaimed
+    )]
+    pub from_position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"user_stats", sender.key().as_ref()],
+        bump = sender_stats.bump
+    )]
+    pub sender_stats: Account<'info, UserStats>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = Position::SIZE,
+        seeds = [b"position", market.key().as_ref(), recipient.key().as_ref()],
+        bump,
+        constraint = !to_position.claimed @ PredictionMarketError::AlreadyClaimed
+    )]
+    pub to_position: Account<'info, Position>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", recipient.key().as_ref()],
+        bump
+    )]
+    pub recipient_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    /// CHECK: any wallet; only used to derive its position and stats
+    #[account(constraint = recipient.key() != sender.key() @ PredictionMarketError::InvalidBetter)]
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves `amount` of the sender's `outcome` stake, with its share of what the
+/// sender invested, to the recipient's position. Pools are unchanged. The
+/// sender's position slot is released once it has no stake left.
+pub fn transfer_position(ctx: Context<TransferPosition>, outcome: Outcome, amount: u64) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let from_position = &mut ctx.accounts.from_position;
+    let to_position = &mut ctx.accounts.to_position;
+    let recipient = ctx.accounts.recipient.key();
+
+    require!(
+        market.can_transfer_positions(Clock::get()?.unix_timestamp),
+        PredictionMarketError::InvalidMarketStatus
+    );
+    ctx.accounts.protocol_config.require_not_paused(market, PauseScope::BETS)?;
+    market.check_user_stake(to_position.get_total_stake(), amount)?;
+
+    let share = from_position.reduce_stake(outcome, amount)?;
+    if !from_position.has_position() {
+        ctx.accounts.sender_stats.close_position();
+    }
+
+    init_user_stats_if_needed(&mut ctx.accounts.recipient_stats, recipient, ctx.bumps.recipient_stats);
+    if to_position.market == Pubkey::default() {
+        to_position.market = market.key();
+        to_position.better = recipient;
+        to_position.claimed = false;
+        to_position.yes_amount = 0;
+        to_position.no_amount = 0;
+        to_position.total_invested = 0;
+        to_position.pending_payout = 0;
+        to_position.fees_paid = 0;
+        to_position.referral_fees = 0;
+        to_position.entry_odds_yes = from_position.entry_odds_yes;
+        to_position.entry_odds_no = from_position.entry_odds_no;
+        to_position.bet_timestamp = from_position.bet_timestamp;
+
+        market.track_position()?;
+    }
+    if !to_position.has_position() {
+        ctx.accounts.recipient_stats.open_position(ctx.accounts.protocol_config.max_open_positions)?;
+    }
+    to_position.add_stake(outcome, amount, share)?;
+
+    emit!(PositionTransferred {
+        market: market.key(),
+        from: ctx.accounts.sender.key(),
+        to: recipient,
+        outcome: outcome as u8,
+        amount,
+    });
+    msg!("Transferred {} {:?} stake to {}", amount, outcome, recipient);
+
+    Ok(())
+}
+
+// ===== RESOLVE MARKET WITH ORACLE VALIDATION =====
+#[derive(Accounts)]
+pub struct ResolveMarket<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: This account is the Pyth oracle price feed. It's validated at runtime when reading price data.
+    pub pyth_feed: AccountInfo<'info>,
+
+    #[account(
+        constraint = resolver.key() == market.resolver_authority @ PredictionMarketError::UnauthorizedResolver
+    )]
+    pub resolver: Signer<'info>,
+
+    pub registry_pages: RegistryPages<'info>,
+}
+
+
+// ===== RESOLVE WITH EXTERNAL PRICE (FOR TESTING) =====
+#[derive(Accounts)]
+pub struct ResolveWithExternalPrice<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        constraint = resolver.key() == market.resolver_authority @ PredictionMarketError::UnauthorizedResolver
+    )]
+    pub resolver: Signer<'info>,
+
+    pub registry_pages: RegistryPages<'info>,
+}
+
+pub fn resolve_with_external_price(
+    ctx: Context<ResolveWithExternalPrice>,
+    final_price: i64,
+) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let clock = Clock::get()?;
+
+    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(
+        market.market_status == MarketStatus::Active,
+        PredictionMarketError::InvalidMarketStatus
+    );
+    require!(
+        clock.unix_timestamp >= market.settle_time,
+        PredictionMarketError::SettlementTimeNotReached
+    );
+
+    let initial_price = market.target_price;
+    let winning_outcome = if market.resolves_yes(final_price) {
+        0 // YES wins (price made the target move)
+    } else {
+        1 // NO wins
+    };
+
+    market.is_resolved = true;
+    market.winning_outcome = Some(winning_outcome);
+    market.final_price = Some(final_price);
+    market.market_status = MarketStatus::Resolved;
+    market.settled_at = clock.unix_timestamp;
+
+    ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
 
     msg!(
-        "Bet placed: {} tokens on {:?}, Fee: {} tokens",
-        amount_after_fee,
-        outcome,
-        fee
+        "Market resolved with external price! Initial: {}, Final: {}, Winner: {}",
+        initial_price,
+        final_price,
+        if winning_outcome == 0 { "YES" } else { "NO" }
     );
-    msg!("New odds - YES: {:.2}%, NO: {:.2}%",
-        market.yes_pool as f64 / market.get_total_pot() as f64 * 100.0,
-        market.no_pool as f64 / market.get_total_pot() as f64 * 100.0
+
+    Ok(())
+}
+
+// ===== FIXED CLAIM WINNINGS =====
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"position", market.key().as_ref(), claimer.key().as_ref()],
+        bump,
+        constraint = position.better == claimer.key(),
+        constraint = !position.claimed @ PredictionMarketError::AlreadyClaimed
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Omit on native SOL markets to be paid in lamports through `unwrap_account`.
+    #[account(
+        mut,
+        constraint = user_token_account.owner == claimer.key(),
+        constraint = user_token_account.mint == market.collateral_mint
+    )]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Temporary wrapped-SOL account the payout passes through; closed to `claimer`.
+    #[account(
+        init,
+        payer = authority,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"unwrap", market.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub unwrap_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", claimer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    /// Set when `authority` is a session key claiming on `claimer`'s behalf.
+    #[account(
+        seeds = [b"betting_delegate", claimer.key().as_ref(), authority.key().as_ref()],
+        bump = betting_delegate.bump
+    )]
+    pub betting_delegate: Option<Account<'info, BettingDelegate>>,
+
+    /// CHECK: owner of the position; receives native SOL payouts
+    #[account(mut)]
+    pub claimer: UncheckedAccount<'info>,
+
+    /// The claimer, or their delegate; pays for any accounts created
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Replace the claim_winnings function in pythpredict/src/instructions.rs with this fixed version
+
+pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let position = &mut ctx.accounts.position;
+
+    // Payouts always go to the claimer, so a delegate only needs to be live
+    match &ctx.accounts.betting_delegate {
+        Some(betting_delegate) => require!(
+            !betting_delegate.is_expired(Clock::get()?.unix_timestamp),
+            PredictionMarketError::DelegateExpired
+        ),
+        None => require_keys_eq!(
+            ctx.accounts.authority.key(),
+            ctx.accounts.claimer.key(),
+            PredictionMarketError::Unauthorized
+        ),
+    }
+
+    // Validate market is resolved
+    require!(market.is_resolved, PredictionMarketError::MarketNotResolved);
+    require!(position.has_position(), PredictionMarketError::NoPosition);
+    ctx.accounts.protocol_config.require_not_paused(market, PauseScope::CLAIMS)?;
+
+    let winning_outcome = market.winning_outcome
+        .ok_or(PredictionMarketError::MarketNotResolved)?;
+
+    let winning_stake = position.get_winning_stake(winning_outcome);
+
+    market.unclaimed_positions = market.unclaimed_positions.saturating_sub(1);
+    ctx.accounts.user_stats.close_position();
+
+    // Native SOL payouts go through the unwrap account; anything else needs a token account
+    let payout_account = match (&ctx.accounts.user_token_account, &ctx.accounts.unwrap_account) {
+        (Some(user_token_account), _) => user_token_account.to_account_info(),
+        (None, Some(unwrap_account)) if market.is_native() => unwrap_account.to_account_info(),
+        _ => return err!(PredictionMarketError::MissingTokenAccount),
+    };
+
+    // If user didn't win, they get nothing
+    if winning_stake == 0 {
+        msg!("User has no winning position");
+        position.claimed = true;
+        if let Some(unwrap_account) = &ctx.accounts.unwrap_account {
+            unwrap_to(&ctx.accounts.token_program, unwrap_account, ctx.accounts.claimer.to_account_info(), market)?;
+        }
+        return Ok(());
+    }
+
+    // Determine winning and losing vaults and pools
+    let (win_vault, lose_vault, win_pool, lose_pool) = match winning_outcome {
+        0 => (
+            &ctx.accounts.yes_vault,
+            &ctx.accounts.no_vault,
+            market.yes_pool,
+            market.no_pool,
+        ),
+        1 => (
+            &ctx.accounts.no_vault,
+            &ctx.accounts.yes_vault,
+            market.no_pool,
+            market.yes_pool,
+        ),
+        _ => return Err(PredictionMarketError::InvalidPool.into()),
+    };
+
+    // Calculate profit (stake * lose_pool / win_pool) using u128 to avoid overflow
+    let profit = if lose_pool == 0 {
+        0
+    } else {
+        let profit_128 = (winning_stake as u128)
+            .checked_mul(lose_pool as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?
+            .checked_div(win_pool as u128)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        profit_128 as u64
+    };
+
+    let payout = winning_stake
+        .checked_add(profit)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    // Create market PDA signer seeds
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[&market_seeds[..]];
+
+    // Transfer stake from winning vault
+    let transfer_win_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        TransferChecked {
+            from: win_vault.to_account_info(),
+            mint: ctx.accounts.collateral_mint.to_account_info(),
+            to: payout_account.clone(),
+            authority: market.to_account_info(),
+        },
+        signer,
+    );
+    token_interface::transfer_checked(transfer_win_ctx, winning_stake, ctx.accounts.collateral_mint.decimals)?;
+
+    // Transfer profit from losing vault (if any)
+    if profit > 0 {
+        let transfer_lose_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: lose_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: payout_account.clone(),
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(transfer_lose_ctx, profit, ctx.accounts.collateral_mint.decimals)?;
+    }
+
+    if let Some(unwrap_account) = &ctx.accounts.unwrap_account {
+        unwrap_to(&ctx.accounts.token_program, unwrap_account, ctx.accounts.claimer.to_account_info(), market)?;
+    }
+
+    // Mark as claimed
+    position.claimed = true;
+    position.pending_payout = 0;
+
+    msg!(
+        "Claimed {} tokens for user {} (stake: {}, profit: {})",
+        payout,
+        ctx.accounts.claimer.key(),
+        winning_stake,
+        profit
     );
 
     Ok(())
 }
-
-// ===== RESOLVE MARKET WITH ORACLE VALIDATION =====
+// ===== CANCEL MARKET & REFUNDS =====
 #[derive(Accounts)]
-pub struct ResolveMarket<'info> {
+pub struct CancelMarket<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
 
-    /// CHECK: This account is the Pyth oracle price feed. It's validated at runtime when reading price data.
-    pub pyth_feed: AccountInfo<'info>,
-
     #[account(
-        constraint = resolver.key() == market.resolver_authority @ PredictionMarketError::UnauthorizedResolver
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
     )]
-    pub resolver: Signer<'info>,
-}
-
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-// ===== RESOLVE WITH EXTERNAL PRICE (FOR TESTING) =====
-#[derive(Accounts)]
-pub struct ResolveWithExternalPrice<'info> {
-    #[account(mut)]
-    pub market: Account<'info, Market>,
+    pub authority: Signer<'info>,
 
-    #[account(
-        constraint = resolver.key() == market.resolver_authority @ PredictionMarketError::UnauthorizedResolver
-    )]
-    pub resolver: Signer<'info>,
+    pub registry_pages: RegistryPages<'info>,
 }
 
-pub fn resolve_with_external_price(
-    ctx: Context<ResolveWithExternalPrice>,
-    final_price: i64,
-) -> Result<()> {
+pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
     let market = &mut ctx.accounts.market;
+    let authority = ctx.accounts.authority.key();
     let clock = Clock::get()?;
 
     require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
-    require!(
-        clock.unix_timestamp >= market.settle_time,
-        PredictionMarketError::SettlementTimeNotReached
-    );
 
-    // For zero-target markets, any price change means YES wins
-    let initial_price = market.target_price;
-    let winning_outcome = if final_price != initial_price {
-        0 // YES wins (price moved)
-    } else {
-        1 // NO wins (price stayed the same)
-    };
+    // Anyone may cancel a market that missed its funding deadline
+    if !market.cancel_if_funding_expired(clock.unix_timestamp) {
+        require!(market.can_cancel(), PredictionMarketError::InvalidMarketStatus);
 
-    market.is_resolved = true;
-    market.winning_outcome = Some(winning_outcome);
-    market.final_price = Some(final_price);
-    market.market_status = MarketStatus::Resolved;
+        if authority != ctx.accounts.protocol_config.admin {
+            // Creators may only back out of markets nobody has funded yet
+            require!(authority == market.creator, PredictionMarketError::Unauthorized);
+            require!(market.total_volume == 0, PredictionMarketError::Unauthorized);
+        }
 
-    msg!(
-        "Market resolved with external price! Initial: {}, Final: {}, Winner: {}",
-        initial_price,
-        final_price,
-        if winning_outcome == 0 { "YES" } else { "NO" }
-    );
+        market.cancel(clock.unix_timestamp);
+    }
+
+    ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
+
+    emit!(MarketCancelled {
+        market: market.key(),
+        authority,
+        timestamp: clock.unix_timestamp,
+    });
+    msg!("Market cancelled by {}", authority);
 
     Ok(())
 }
 
-// ===== FIXED CLAIM WINNINGS =====
 #[derive(Accounts)]
-pub struct ClaimWinnings<'info> {
+pub struct ClaimRefund<'info> {
     #[account(mut)]
     pub market: Account<'info, Market>,
 
@@ -473,131 +4180,517 @@ pub struct ClaimWinnings<'info> {
         seeds = [b"yes_vault", market.key().as_ref()],
         bump
     )]
-    pub yes_vault: Account<'info, TokenAccount>,
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         seeds = [b"no_vault", market.key().as_ref()],
         bump
     )]
-    pub no_vault: Account<'info, TokenAccount>,
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
 
+    /// Omit on native SOL markets to be refunded in lamports through `unwrap_account`.
     #[account(
         mut,
         constraint = user_token_account.owner == claimer.key(),
         constraint = user_token_account.mint == market.collateral_mint
     )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Temporary wrapped-SOL account the refund passes through; closed to `claimer`.
+    #[account(
+        init,
+        payer = claimer,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"unwrap", market.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub unwrap_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", claimer.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    pub registry_pages: RegistryPages<'info>,
+}
+
+pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let position = &mut ctx.accounts.position;
+    let clock = Clock::get()?;
+
+    // Markets that never reached their minimum liquidity cancel on first refund
+    if market.cancel_if_funding_expired(clock.unix_timestamp) {
+        emit!(MarketCancelled {
+            market: market.key(),
+            authority: ctx.accounts.claimer.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
+        msg!("Funding deadline passed, market cancelled");
+    }
+
+    require!(market.is_cancelled(), PredictionMarketError::MarketNotCancelled);
+    require!(position.has_position(), PredictionMarketError::NoPosition);
+    ctx.accounts.protocol_config.require_not_paused(market, PauseScope::CLAIMS)?;
+
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let signer = &[market_seeds];
+
+    // Native SOL refunds go through the unwrap account; anything else needs a token account
+    let payout_account = match (&ctx.accounts.user_token_account, &ctx.accounts.unwrap_account) {
+        (Some(user_token_account), _) => user_token_account.to_account_info(),
+        (None, Some(unwrap_account)) if market.is_native() => unwrap_account.to_account_info(),
+        _ => return err!(PredictionMarketError::MissingTokenAccount),
+    };
+
+    // Stakes come back from their pools, the fee and escrowed referral share from the fee vault
+    let fee_refund = position.get_fees_paid()
+        .checked_add(position.referral_fees)
+        .ok_or(PredictionMarketError::MathOverflow)?
+        .min(ctx.accounts.fee_vault.amount);
+    let refunds = [
+        (&ctx.accounts.yes_vault, position.yes_amount),
+        (&ctx.accounts.no_vault, position.no_amount),
+        (&ctx.accounts.fee_vault, fee_refund),
+    ];
+    for (vault, amount) in refunds {
+        if amount == 0 {
+            continue;
+        }
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
+                to: payout_account.clone(),
+                authority: market.to_account_info(),
+            },
+            signer,
+        );
+        token_interface::transfer_checked(transfer_ctx, amount, ctx.accounts.collateral_mint.decimals)?;
+    }
+
+    if let Some(unwrap_account) = &ctx.accounts.unwrap_account {
+        unwrap_to(&ctx.accounts.token_program, unwrap_account, ctx.accounts.claimer.to_account_info(), market)?;
+    }
+
+    let refunded = position.get_total_stake()
+        .checked_add(fee_refund)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    market.yes_pool = market.yes_pool.saturating_sub(position.yes_amount);
+    market.no_pool = market.no_pool.saturating_sub(position.no_amount);
+    market.total_fees_collected = market.total_fees_collected.saturating_sub(position.get_fees_paid());
+    market.referral_escrow = market.referral_escrow.saturating_sub(position.referral_fees);
+    market.unclaimed_positions = market.unclaimed_positions.saturating_sub(1);
+    ctx.accounts.user_stats.close_position();
+
+    position.claimed = true;
+    position.pending_payout = 0;
+    position.referral_fees = 0;
+
+    emit!(RefundClaimed {
+        market: market.key(),
+        better: ctx.accounts.claimer.key(),
+        amount: refunded,
+    });
+    msg!("Refunded {} tokens to {}", refunded, ctx.accounts.claimer.key());
+
+    Ok(())
+}
+
+// ===== CREATOR BOND =====
+#[derive(Accounts)]
+pub struct ReclaimBond<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PredictionMarketError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Receives the bond. Omit on native SOL markets to be paid in lamports.
+    #[account(
+        mut,
+        constraint = creator_token_account.owner == creator.key(),
+        constraint = creator_token_account.mint == market.collateral_mint
+    )]
+    pub creator_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Writable when the mint charges a transfer fee, so withheld fees can be
+    /// harvested before the vault closes
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Returns the creator bond once the market has resolved (or was cancelled
+/// without being slashed) and the dispute window has passed, and closes the
+/// bond vault.
+pub fn reclaim_bond(ctx: Context<ReclaimBond>) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+
+    require!(
+        market.is_resolved || market.is_cancelled(),
+        PredictionMarketError::MarketNotResolved
+    );
+    require!(!market.bond_settled, PredictionMarketError::BondAlreadySettled);
+    require!(
+        market.can_reclaim_bond(Clock::get()?.unix_timestamp),
+        PredictionMarketError::BondDisputeWindow
+    );
+
+    let amount = drain_vault(
+        &ctx.accounts.token_program,
+        &ctx.accounts.collateral_mint,
+        &ctx.accounts.bond_vault,
+        ctx.accounts.creator_token_account.as_ref().map(|account| account.to_account_info()),
+        ctx.accounts.creator.to_account_info(),
+        market,
+    )?;
+
+    market.bond_amount = 0;
+    market.bond_settled = true;
+
+    emit!(BondReturned {
+        market: market.key(),
+        creator: market.creator,
+        amount,
+    });
+    msg!("Returned creator bond of {} tokens", amount);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SlashBond<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        has_one = admin @ PredictionMarketError::Unauthorized
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [b"fee_collector"],
+        bump
+    )]
+    pub fee_collector: Account<'info, FeeCollector>,
+
+    #[account(
+        mut,
+        constraint = treasury_account.owner == fee_collector.treasury @ PredictionMarketError::InvalidOwner,
+        constraint = treasury_account.mint == market.collateral_mint @ PredictionMarketError::InvalidMint
+    )]
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Writable when the mint charges a transfer fee, so withheld fees can be
+    /// harvested before the vault closes
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub registry_pages: RegistryPages<'info>,
+}
+
+/// Admin marks a market invalid or spam: the bond goes to the treasury and an
+/// unresolved market is cancelled so bettors can claim refunds.
+pub fn slash_bond(ctx: Context<SlashBond>) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let clock = Clock::get()?;
+
+    require!(!market.bond_settled, PredictionMarketError::BondAlreadySettled);
+
+    if !market.is_resolved && !market.is_cancelled() {
+        market.cancel(clock.unix_timestamp);
+        ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
+        emit!(MarketCancelled {
+            market: market.key(),
+            authority: ctx.accounts.admin.key(),
+            timestamp: clock.unix_timestamp,
+        });
+    }
+
+    // Vault rent goes to the admin doing the cleanup
+    let amount = drain_vault(
+        &ctx.accounts.token_program,
+        &ctx.accounts.collateral_mint,
+        &ctx.accounts.bond_vault,
+        Some(ctx.accounts.treasury_account.to_account_info()),
+        ctx.accounts.admin.to_account_info(),
+        market,
+    )?;
+
+    market.bond_amount = 0;
+    market.bond_settled = true;
+
+    emit!(BondSlashed {
+        market: market.key(),
+        authority: ctx.accounts.admin.key(),
+        amount,
+        timestamp: clock.unix_timestamp,
+    });
+    msg!("Slashed creator bond of {} tokens to treasury", amount);
+
+    Ok(())
+}
+
+// ===== RELEASE REFERRAL FEES =====
+#[derive(Accounts)]
+pub struct ReleaseReferralFees<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"position", market.key().as_ref(), position.better.as_ref()],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    /// The position owner's stats, which record their referrer
+    #[account(
+        seeds = [b"user_stats", position.better.as_ref()],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump,
+        constraint = user_stats.referrer == Some(referrer.wallet) @ PredictionMarketError::InvalidReferrer
+    )]
+    pub referrer: Account<'info, Referrer>,
+
+    #[account(
+        mut,
+        seeds = [b"referral_vault", referrer.key().as_ref(), market.collateral_mint.as_ref()],
+        bump
+    )]
+    pub referral_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Permissionless: pays a position's escrowed referral share from the fee
+/// vault to the referrer once it can no longer be refunded.
+pub fn release_referral_fees(ctx: Context<ReleaseReferralFees>) -> Result<()> {
+    let accounts = &mut *ctx.accounts;
+    let market = &mut accounts.market;
+    let position = &mut accounts.position;
+    require!(
+        position.can_release_referral_fees(market),
+        PredictionMarketError::InvalidMarketStatus
+    );
+
+    let nonce_bytes = market.nonce.to_le_bytes();
+    let market_seeds: &[&[u8]] = &[
+        b"market",
+        market.creator.as_ref(),
+        &nonce_bytes,
+        &[market.bump],
+    ];
+    let amount = position.referral_fees;
+    let landed = transfer_in(
+        &accounts.token_program,
+        &accounts.collateral_mint,
+        &accounts.fee_vault,
+        &mut accounts.referral_vault,
+        market.to_account_info(),
+        &[market_seeds],
+        amount,
+    )?;
+
+    market.referral_escrow = market.referral_escrow.saturating_sub(amount);
+    position.referral_fees = 0;
+    accounts.referrer.fees_earned = accounts.referrer.fees_earned
+        .checked_add(landed)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+
+    emit!(ReferralFeesReleased {
+        market: market.key(),
+        better: position.better,
+        referrer: accounts.referrer.wallet,
+        amount: landed,
+    });
+    msg!("Released {} tokens of referral fees to {}", landed, accounts.referrer.wallet);
+
+    Ok(())
+}
+
+// ===== CLOSE POSITION (RECLAIM RENT) =====
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"position", market.key().as_ref(), better.key().as_ref()],
+        bump,
+        constraint = position.better == better.key() @ PredictionMarketError::InvalidBetter,
+        close = better
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        init_if_needed,
+        payer = better,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", better.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
 
-    pub claimer: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    #[account(mut)]
+    pub better: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-// Replace the claim_winnings function in pythpredict/src/instructions.rs with this fixed version
+pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let position = &ctx.accounts.position;
 
-pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-    let market = &ctx.accounts.market;
-    let position = &mut ctx.accounts.position;
+    require!(position.is_settled(market), PredictionMarketError::PositionNotSettled);
+    require!(position.referral_fees == 0, PredictionMarketError::ReferralFeesOutstanding);
 
-    // Validate market is resolved
-    require!(market.is_resolved, PredictionMarketError::MarketNotResolved);
-    require!(position.has_position(), PredictionMarketError::NoPosition);
+    // Losers can close without a separate claim; release their slot here.
+    // Emptied positions released theirs when the last stake left.
+    if position.holds_slot() {
+        ctx.accounts.user_stats.close_position();
+    }
+    market.untrack_position(position.claimed);
 
-    let winning_outcome = market.winning_outcome
-        .ok_or(PredictionMarketError::MarketNotResolved)?;
+    msg!("Position closed, rent returned to {}", ctx.accounts.better.key());
 
-    let winning_stake = position.get_winning_stake(winning_outcome);
+    Ok(())
+}
 
-    // If user didn't win, they get nothing
-    if winning_stake == 0 {
-        msg!("User has no winning position");
-        position.claimed = true;
-        return Ok(());
-    }
+// ===== SWEEP POSITION =====
+/// Closes a position left open past the sweep deadline so the market can be
+/// closed; its unclaimed stake and any unreleased referral share are
+/// forfeited with the rest of the sweep.
+#[derive(Accounts)]
+pub struct SweepPosition<'info> {
+    #[account(mut)]
+    pub market: Account<'info, Market>,
 
-    // Determine winning and losing vaults and pools
-    let (win_vault, lose_vault, win_pool, lose_pool) = match winning_outcome {
-        0 => (
-            &ctx.accounts.yes_vault,
-            &ctx.accounts.no_vault,
-            market.yes_pool,
-            market.no_pool,
-        ),
-        1 => (
-            &ctx.accounts.no_vault,
-            &ctx.accounts.yes_vault,
-            market.no_pool,
-            market.yes_pool,
-        ),
-        _ => return Err(PredictionMarketError::InvalidPool.into()),
-    };
+    #[account(
+        mut,
+        seeds = [b"position", market.key().as_ref(), better.key().as_ref()],
+        bump,
+        constraint = position.better == better.key() @ PredictionMarketError::InvalidBetter,
+        close = better
+    )]
+    pub position: Account<'info, Position>,
 
-    // Calculate profit (stake * lose_pool / win_pool) using u128 to avoid overflow
-    let profit = if lose_pool == 0 {
-        0
-    } else {
-        let profit_128 = (winning_stake as u128)
-            .checked_mul(lose_pool as u128)
-            .ok_or(PredictionMarketError::MathOverflow)?
-            .checked_div(win_pool as u128)
-            .ok_or(PredictionMarketError::MathOverflow)?;
-        profit_128 as u64
-    };
+    #[account(
+        mut,
+        seeds = [b"user_stats", better.key().as_ref()],
+        bump = user_stats.bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
 
-    let payout = winning_stake
-        .checked_add(profit)
-        .ok_or(PredictionMarketError::MathOverflow)?;
+    /// CHECK: Position owner; only receives the rent
+    #[account(mut)]
+    pub better: UncheckedAccount<'info>,
 
-    // Create market PDA signer seeds
-    let nonce_bytes = market.nonce.to_le_bytes();
-    let market_seeds = &[
-        b"market",
-        market.creator.as_ref(),
-        &nonce_bytes,
-        &[market.bump],
-    ];
-    let signer = &[&market_seeds[..]];
+    pub caller: Signer<'info>,
+}
 
-    // Transfer stake from winning vault
-    let transfer_win_ctx = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        Transfer {
-            from: win_vault.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
-            authority: market.to_account_info(),
-        },
-        signer,
+pub fn sweep_position(ctx: Context<SweepPosition>) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+    let position = &ctx.accounts.position;
+
+    require!(
+        market.is_resolved || market.is_cancelled(),
+        PredictionMarketError::MarketNotResolved
+    );
+    require!(
+        Clock::get()?.unix_timestamp >= market.sweep_deadline(),
+        PredictionMarketError::UnclaimedPositions
     );
-    token::transfer(transfer_win_ctx, winning_stake)?;
 
-    // Transfer profit from losing vault (if any)
-    if profit > 0 {
-        let transfer_lose_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: lose_vault.to_account_info(),
-                to: ctx.accounts.user_token_account.to_account_info(),
-                authority: market.to_account_info(),
-            },
-            signer,
-        );
-        token::transfer(transfer_lose_ctx, profit)?;
+    if position.holds_slot() {
+        ctx.accounts.user_stats.close_position();
     }
+    market.untrack_position(position.claimed);
+    // An unreleased referral share goes to the treasury with the fee vault
+    market.referral_escrow = market.referral_escrow.saturating_sub(position.referral_fees);
 
-    // Mark as claimed
-    position.claimed = true;
-    position.pending_payout = 0;
-
-    msg!(
-        "Claimed {} tokens for user {} (stake: {}, profit: {})",
-        payout,
-        ctx.accounts.claimer.key(),
-        winning_stake,
-        profit
-    );
+    msg!("Position swept, rent returned to {}", ctx.accounts.better.key());
 
     Ok(())
 }
+
 // ===== MARKET MAKER FUNCTIONS =====
 #[derive(Accounts)]
 pub struct InitializeMarketMaker<'info> {
@@ -666,26 +4759,46 @@ pub struct ProvideLiquidity<'info> {
         mut,
         constraint = provider_token_account.owner == liquidity_provider.key()
     )]
-    pub provider_token_account: Account<'info, TokenAccount>,
+    pub provider_token_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         seeds = [b"yes_vault", market.key().as_ref()],
         bump
     )]
-    pub yes_vault: Account<'info, TokenAccount>,
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         seeds = [b"no_vault", market.key().as_ref()],
         bump
     )]
-    pub no_vault: Account<'info, TokenAccount>,
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = liquidity_provider,
+        space = UserStats::SIZE,
+        seeds = [b"user_stats", liquidity_provider.key().as_ref()],
+        bump
+    )]
+    pub user_stats: Account<'info, UserStats>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     pub liquidity_provider: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
+
+    pub registry_pages: RegistryPages<'info>,
 }
 
 pub fn provide_liquidity(ctx: Context<ProvideLiquidity>, amount_per_side: u64) -> Result<()> {
@@ -695,51 +4808,80 @@ pub fn provide_liquidity(ctx: Context<ProvideLiquidity>, amount_per_side: u64) -
     let clock = Clock::get()?;
 
     require!(market_maker.is_active, PredictionMarketError::MarketNotActive);
+    // Liquidity is held in a Position, which tokenized markets do not use
+    require!(!market.tokenized, PredictionMarketError::TokenizedMarket);
+    require!(!market.is_resolved, PredictionMarketError::MarketAlreadyResolved);
+    require!(!market.is_cancelled(), PredictionMarketError::MarketNotActive);
+    require!(
+        !market.is_funding_expired(clock.unix_timestamp),
+        PredictionMarketError::FundingDeadlinePassed
+    );
+    require!(
+        market.is_betting_open(clock.unix_timestamp),
+        PredictionMarketError::MarketClosed
+    );
+    ctx.accounts.protocol_config.require_not_paused(market, PauseScope::LIQUIDITY)?;
+
+    let amount_total = amount_per_side
+        .checked_mul(2)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+    market.check_capacity(amount_total)?;
+    market.check_user_stake(mm_position.get_total_stake(), amount_total)?;
 
-    // Check exposure limits
-    let total_exposure = market_maker.current_exposure + (amount_per_side * 2);
+    // Check exposure limits against the requested amount, an upper bound on what lands
+    let total_exposure = market_maker.current_exposure
+        .checked_add(amount_total)
+        .ok_or(PredictionMarketError::MathOverflow)?;
     require!(
         total_exposure <= market_maker.max_exposure,
         PredictionMarketError::BetTooLarge
     );
 
-    // Transfer to YES vault
-    let yes_transfer = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        Transfer {
-            from: ctx.accounts.provider_token_account.to_account_info(),
-            to: ctx.accounts.yes_vault.to_account_info(),
-            authority: ctx.accounts.liquidity_provider.to_account_info(),
-        },
-    );
-    token::transfer(yes_transfer, amount_per_side)?;
-
-    // Transfer to NO vault
-    let no_transfer = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        Transfer {
-            from: ctx.accounts.provider_token_account.to_account_info(),
-            to: ctx.accounts.no_vault.to_account_info(),
-            authority: ctx.accounts.liquidity_provider.to_account_info(),
-        },
-    );
-    token::transfer(no_transfer, amount_per_side)?;
+    // Each side is credited with what landed after any transfer fee
+    let yes_landed = transfer_in(
+        &ctx.accounts.token_program,
+        &ctx.accounts.collateral_mint,
+        &ctx.accounts.provider_token_account,
+        &mut ctx.accounts.yes_vault,
+        ctx.accounts.liquidity_provider.to_account_info(),
+        &[],
+        amount_per_side,
+    )?;
+    let no_landed = transfer_in(
+        &ctx.accounts.token_program,
+        &ctx.accounts.collateral_mint,
+        &ctx.accounts.provider_token_account,
+        &mut ctx.accounts.no_vault,
+        ctx.accounts.liquidity_provider.to_account_info(),
+        &[],
+        amount_per_side,
+    )?;
 
     // Update market pools
     market.yes_pool = market.yes_pool
-        .checked_add(amount_per_side)
+        .checked_add(yes_landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
     market.no_pool = market.no_pool
-        .checked_add(amount_per_side)
+        .checked_add(no_landed)
+        .ok_or(PredictionMarketError::MathOverflow)?;
+    let landed = yes_landed
+        .checked_add(no_landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
     market.total_volume = market.total_volume
-        .checked_add(amount_per_side * 2)
+        .checked_add(landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
 
+    if market.activate_if_funded() {
+        ctx.accounts.registry_pages.update(market, Some(market.market_status))?;
+        msg!("Minimum liquidity reached, market is now active");
+    }
+
     // Update market maker tracking
-    market_maker.current_exposure = total_exposure;
+    market_maker.current_exposure = market_maker.current_exposure
+        .checked_add(landed)
+        .ok_or(PredictionMarketError::MathOverflow)?;
     market_maker.total_volume_provided = market_maker.total_volume_provided
-        .checked_add(amount_per_side * 2)
+        .checked_add(landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
     market_maker.last_rebalance = clock.unix_timestamp;
 
@@ -752,17 +4894,26 @@ pub fn provide_liquidity(ctx: Context<ProvideLiquidity>, amount_per_side: u64) -
         mm_position.no_amount = 0;
         mm_position.total_invested = 0;
         mm_position.pending_payout = 0;
+        mm_position.fees_paid = 0;
+        mm_position.referral_fees = 0;
+
+        market.track_position()?;
+    }
+    let user_stats = &mut ctx.accounts.user_stats;
+    init_user_stats_if_needed(user_stats, ctx.accounts.liquidity_provider.key(), ctx.bumps.user_stats);
+    if !mm_position.has_position() {
+        user_stats.open_position(ctx.accounts.protocol_config.max_open_positions)?;
     }
 
     // Update MM position
     mm_position.yes_amount = mm_position.yes_amount
-        .checked_add(amount_per_side)
+        .checked_add(yes_landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
     mm_position.no_amount = mm_position.no_amount
-        .checked_add(amount_per_side)
+        .checked_add(no_landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
     mm_position.total_invested = mm_position.total_invested
-        .checked_add(amount_per_side * 2)
+        .checked_add(landed)
         .ok_or(PredictionMarketError::MathOverflow)?;
     mm_position.bet_timestamp = clock.unix_timestamp;
 
@@ -786,7 +4937,7 @@ pub struct ConsolidateFunds<'info> {
         seeds = [b"fee_vault", market.key().as_ref()],
         bump
     )]
-    pub fee_vault: Account<'info, TokenAccount>,
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
     pub fee_collector: Account<'info, FeeCollector>,
@@ -795,13 +4946,16 @@ pub struct ConsolidateFunds<'info> {
         mut,
         constraint = treasury_account.owner == fee_collector.treasury
     )]
-    pub treasury_account: Account<'info, TokenAccount>,
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         constraint = authority.key() == fee_collector.authority @ PredictionMarketError::UnauthorizedResolver
     )]
     pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 // pythpredict/src/instructions.rs (inside consolidate_funds)
@@ -809,7 +4963,8 @@ pub fn consolidate_funds(ctx: Context<ConsolidateFunds>) -> Result<()> {
     let market = &ctx.accounts.market;
     require!(market.is_resolved, PredictionMarketError::MarketNotResolved);
 
-    let fee_vault_balance = ctx.accounts.fee_vault.amount;
+    // Escrowed referral shares stay until released to their referrers
+    let fee_vault_balance = ctx.accounts.fee_vault.amount.saturating_sub(market.referral_escrow);
     if fee_vault_balance > 0 {
         // MARKET is the token authority for fee_vault → sign with MARKET seeds, not fee_vault seeds
         let nonce_bytes = market.nonce.to_le_bytes();
@@ -823,14 +4978,15 @@ pub fn consolidate_funds(ctx: Context<ConsolidateFunds>) -> Result<()> {
 
         let transfer_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: ctx.accounts.fee_vault.to_account_info(),
+                mint: ctx.accounts.collateral_mint.to_account_info(),
                 to: ctx.accounts.treasury_account.to_account_info(),
                 authority: market.to_account_info(),
             },
             signer,
         );
-        token::transfer(transfer_ctx, fee_vault_balance)?;
+        token_interface::transfer_checked(transfer_ctx, fee_vault_balance, ctx.accounts.collateral_mint.decimals)?;
 
         ctx.accounts.fee_collector.total_fees_collected =
             ctx.accounts.fee_collector.total_fees_collected
@@ -840,3 +4996,206 @@ pub fn consolidate_funds(ctx: Context<ConsolidateFunds>) -> Result<()> {
     }
     Ok(())
 }
+
+
+// ===== CLOSE MARKET (RECLAIM RENT) =====
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PredictionMarketError::Unauthorized,
+        close = creator
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"yes_vault", market.key().as_ref()],
+        bump
+    )]
+    pub yes_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"no_vault", market.key().as_ref()],
+        bump
+    )]
+    pub no_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"fee_vault", market.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required once share mints exist; unredeemed set collateral is swept too
+    #[account(
+        mut,
+        seeds = [b"set_vault", market.key().as_ref()],
+        bump
+    )]
+    pub set_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required once an order book exists
+    #[account(
+        mut,
+        seeds = [b"order_vault", market.key().as_ref()],
+        bump
+    )]
+    pub order_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Required, with its escrow and share mint, once the YES order book exists
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref(), &[Outcome::Yes as u8]],
+        bump = yes_order_book.bump,
+        close = creator
+    )]
+    pub yes_order_book: Option<Box<Account<'info, OrderBook>>>,
+
+    #[account(
+        mut,
+        seeds = [b"share_escrow", market.key().as_ref(), &[Outcome::Yes as u8]],
+        bump
+    )]
+    pub yes_share_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [Outcome::Yes.share_mint_seed(), market.key().as_ref()],
+        bump
+    )]
+    pub yes_share_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// Required, with its escrow and share mint, once the NO order book exists
+    #[account(
+        mut,
+        seeds = [b"order_book", market.key().as_ref(), &[Outcome::No as u8]],
+        bump = no_order_book.bump,
+        close = creator
+    )]
+    pub no_order_book: Option<Box<Account<'info, OrderBook>>>,
+
+    #[account(
+        mut,
+        seeds = [b"share_escrow", market.key().as_ref(), &[Outcome::No as u8]],
+        bump
+    )]
+    pub no_share_escrow: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        seeds = [Outcome::No.share_mint_seed(), market.key().as_ref()],
+        bump
+    )]
+    pub no_share_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(
+        seeds = [b"fee_collector"],
+        bump
+    )]
+    pub fee_collector: Account<'info, FeeCollector>,
+
+    /// Receives fees and any winnings left unclaimed past the sweep deadline
+    #[account(
+        mut,
+        constraint = treasury_account.owner == fee_collector.treasury @ PredictionMarketError::InvalidOwner,
+        constraint = treasury_account.mint == market.collateral_mint @ PredictionMarketError::InvalidMint
+    )]
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Writable when the mint charges a transfer fee, so withheld fees can be
+    /// harvested before the vault closes
+    #[account(address = market.collateral_mint @ PredictionMarketError::InvalidMint)]
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub registry_pages: RegistryPages<'info>,
+}
+
+pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+    let clock = Clock::get()?;
+
+    // An unfunded market past its deadline is cancelled and closed in one go
+    if ctx.accounts.market.cancel_if_funding_expired(clock.unix_timestamp) {
+        emit!(MarketCancelled {
+            market: ctx.accounts.market.key(),
+            authority: ctx.accounts.creator.key(),
+            timestamp: clock.unix_timestamp,
+        });
+    }
+    let market = &ctx.accounts.market;
+
+    require!(
+        market.is_resolved || market.is_cancelled(),
+        PredictionMarketError::MarketNotResolved
+    );
+    require!(
+        market.can_close(clock.unix_timestamp),
+        PredictionMarketError::UnclaimedPositions
+    );
+    require!(market.bond_settled, PredictionMarketError::BondOutstanding);
+
+    ctx.accounts.registry_pages.update(market, None)?;
+
+    require!(
+        market.complete_sets == 0 || ctx.accounts.set_vault.is_some(),
+        PredictionMarketError::MissingTokenAccount
+    );
+    require!(
+        market.order_books == 0 || ctx.accounts.order_vault.is_some(),
+        PredictionMarketError::MissingTokenAccount
+    );
+    let books = [
+        (Outcome::Yes, &ctx.accounts.yes_order_book, &ctx.accounts.yes_share_escrow, &ctx.accounts.yes_share_mint),
+        (Outcome::No, &ctx.accounts.no_order_book, &ctx.accounts.no_share_escrow, &ctx.accounts.no_share_mint),
+    ];
+    for (outcome, book, escrow, share_mint) in books {
+        if !market.has_order_book(outcome) {
+            continue;
+        }
+        let (Some(_), Some(escrow), Some(share_mint)) = (book, escrow, share_mint) else {
+            return err!(PredictionMarketError::MissingTokenAccount);
+        };
+        // Nothing is owed to traders, so any shares left here were donated
+        close_share_escrow(
+            &ctx.accounts.token_program,
+            share_mint,
+            escrow,
+            ctx.accounts.creator.to_account_info(),
+            market,
+        )?;
+    }
+
+    let vaults = [
+        &ctx.accounts.yes_vault,
+        &ctx.accounts.no_vault,
+        &ctx.accounts.fee_vault,
+    ]
+    .into_iter()
+    .chain(ctx.accounts.set_vault.as_ref())
+    .chain(ctx.accounts.order_vault.as_deref());
+    let mut swept = 0u64;
+    for vault in vaults {
+        // Rounding dust, fees and forfeited winnings go to the treasury
+        let amount = drain_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.collateral_mint,
+            vault,
+            Some(ctx.accounts.treasury_account.to_account_info()),
+            ctx.accounts.creator.to_account_info(),
+            market,
+        )?;
+        swept = swept
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+    }
+
+    msg!("Market closed: swept {} tokens to treasury", swept);
+
+    Ok(())
+}
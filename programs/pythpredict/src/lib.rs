@@ -3,72 +3,308 @@ use anchor_lang::prelude::*;
 declare_id!("J7TLVPzbd47RpiHV8BBPLQuixU53P5qijkrwkvN4u98W");
 
 pub mod errors;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
-use state::Outcome;
+use state::{BetOrder, CollateralConfigParams, MarketCategory, MarketConfig, MarketTemplateParams, OrderSide, Outcome};
 pub use instructions::*;
 
-#[program]
-pub mod pythpredict {
+// `#[program]` also expands Anchor's IDL instructions, which still call the
+// deprecated `AccountInfo::realloc`; the allow stops at this module.
+#[allow(deprecated)]
+mod dispatch {
     use super::*;
 
-    pub fn initialize_market(
-        ctx: Context<InitializeMarket>,
-        market_nonce: u64,
-        initial_price: i64,
-        target_change_bps: i64,  // Add this parameter
-        settle_time: i64,
-        resolver_authority: Option<Pubkey>,
-    ) -> Result<()> {
-        instructions::initialize_market(
-            ctx,
-            market_nonce,
-            initial_price,
-            target_change_bps,  // Pass it through
-            settle_time,
-            resolver_authority
-        )
-    }
+    #[program]
+    pub mod pythpredict {
+        use super::*;
 
-    pub fn place_bet(ctx: Context<Bet>, amount: u64, outcome: Outcome) -> Result<()> {
-        instructions::place_bet(ctx, amount, outcome)
-    }
+        pub fn initialize_protocol_config(
+            ctx: Context<InitializeProtocolConfig>,
+            pauser: Pubkey,
+        ) -> Result<()> {
+            instructions::initialize_protocol_config(ctx, pauser)
+        }
 
-    pub fn resolve_with_external_price(
-        ctx: Context<ResolveWithExternalPrice>,
-        final_price: i64,
-    ) -> Result<()> {
-        instructions::resolve_with_external_price(ctx, final_price)
-    }
+        pub fn set_pauser(ctx: Context<UpdateProtocolConfig>, pauser: Pubkey) -> Result<()> {
+            instructions::set_pauser(ctx, pauser)
+        }
 
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-        instructions::claim_winnings(ctx)
-    }
+        pub fn set_max_open_positions(ctx: Context<UpdateProtocolConfig>, max_open_positions: u32) -> Result<()> {
+            instructions::set_max_open_positions(ctx, max_open_positions)
+        }
 
-    // Keep old resolve_market for backwards compatibility if needed
-    pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
-        instructions::resolve_market(ctx)
-    }
+        pub fn set_referral_share(ctx: Context<UpdateProtocolConfig>, referral_share_bps: u16) -> Result<()> {
+            instructions::set_referral_share(ctx, referral_share_bps)
+        }
 
-    pub fn initialize_market_maker(
-        ctx: Context<InitializeMarketMaker>,
-        target_spread_bps: u64,
-        max_exposure: u64,
-    ) -> Result<()> {
-        instructions::initialize_market_maker(ctx, target_spread_bps, max_exposure)
-    }
+        pub fn pause_protocol(ctx: Context<SetProtocolPause>, scope: u8) -> Result<()> {
+            instructions::pause_protocol(ctx, scope)
+        }
 
-    pub fn provide_liquidity(
-        ctx: Context<ProvideLiquidity>,
-        amount_per_side: u64,
-    ) -> Result<()> {
-        instructions::provide_liquidity(ctx, amount_per_side)
-    }
+        pub fn unpause_protocol(ctx: Context<SetProtocolPause>) -> Result<()> {
+            instructions::unpause_protocol(ctx)
+        }
+
+        pub fn pause_market(ctx: Context<SetMarketPause>, scope: u8) -> Result<()> {
+            instructions::pause_market(ctx, scope)
+        }
+
+        pub fn unpause_market(ctx: Context<SetMarketPause>) -> Result<()> {
+            instructions::unpause_market(ctx)
+        }
+
+        pub fn set_collateral_config(
+            ctx: Context<SetCollateralConfig>,
+            params: CollateralConfigParams,
+        ) -> Result<()> {
+            instructions::set_collateral_config(ctx, params)
+        }
+
+        pub fn initialize_market(
+            ctx: Context<InitializeMarket>,
+            market_nonce: Option<u64>,
+            initial_price: i64,
+            target_change_bps: i64,  // Add this parameter
+            settle_time: i64,
+            resolver_authority: Option<Pubkey>,
+            config: MarketConfig,
+        ) -> Result<()> {
+            instructions::initialize_market(
+                ctx,
+                market_nonce,
+                initial_price,
+                target_change_bps,  // Pass it through
+                settle_time,
+                resolver_authority,
+                config,
+            )
+        }
+
+        pub fn sync_registry_entries(ctx: Context<SyncRegistryEntries>) -> Result<()> {
+            instructions::sync_registry_entries(ctx)
+        }
+
+        pub fn create_market_template(
+            ctx: Context<CreateMarketTemplate>,
+            template_id: u64,
+            resolver_authority: Pubkey,
+            params: MarketTemplateParams,
+        ) -> Result<()> {
+            instructions::create_market_template(ctx, template_id, resolver_authority, params)
+        }
+
+        pub fn set_market_template_active(
+            ctx: Context<SetMarketTemplateActive>,
+            is_active: bool,
+        ) -> Result<()> {
+            instructions::set_market_template_active(ctx, is_active)
+        }
+
+        pub fn instantiate_from_template(
+            ctx: Context<InstantiateFromTemplate>,
+            market_nonce: u64,
+        ) -> Result<()> {
+            instructions::instantiate_from_template(ctx, market_nonce)
+        }
+
+        pub fn update_market_metadata(
+            ctx: Context<UpdateMarketMetadata>,
+            title: String,
+            category: MarketCategory,
+            metadata_uri: String,
+            metadata_hash: [u8; 32],
+        ) -> Result<()> {
+            instructions::update_market_metadata(ctx, title, category, metadata_uri, metadata_hash)
+        }
+
+        pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+            instructions::register_referrer(ctx)
+        }
+
+        pub fn open_referral_vault(ctx: Context<OpenReferralVault>) -> Result<()> {
+            instructions::open_referral_vault(ctx)
+        }
+
+        pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+            instructions::claim_referral_fees(ctx)
+        }
+
+        pub fn approve_betting_delegate(
+            ctx: Context<ApproveBettingDelegate>,
+            delegate: Pubkey,
+            total_cap: u64,
+            per_bet_cap: u64,
+            expires_at: i64,
+        ) -> Result<()> {
+            instructions::approve_betting_delegate(ctx, delegate, total_cap, per_bet_cap, expires_at)
+        }
+
+        pub fn revoke_betting_delegate(ctx: Context<RevokeBettingDelegate>) -> Result<()> {
+            instructions::revoke_betting_delegate(ctx)
+        }
+
+        pub fn place_bet(
+            ctx: Context<Bet>,
+            amount: u64,
+            outcome: Outcome,
+            min_expected_payout: Option<u64>,
+            expires_at: Option<i64>,
+        ) -> Result<()> {
+            instructions::place_bet(ctx, amount, outcome, min_expected_payout, expires_at)
+        }
+
+        pub fn place_bets<'info>(
+            ctx: Context<'_, '_, 'info, 'info, PlaceBets<'info>>,
+            bets: Vec<BetOrder>,
+            expires_at: Option<i64>,
+        ) -> Result<()> {
+            instructions::place_bets(ctx, bets, expires_at)
+        }
+
+        pub fn initialize_outcome_mints(ctx: Context<InitializeOutcomeMints>) -> Result<()> {
+            instructions::initialize_outcome_mints(ctx)
+        }
+
+        pub fn buy_outcome_tokens(
+            ctx: Context<BuyOutcomeTokens>,
+            amount: u64,
+            outcome: Outcome,
+            min_expected_payout: Option<u64>,
+            expires_at: Option<i64>,
+        ) -> Result<()> {
+            instructions::buy_outcome_tokens(ctx, amount, outcome, min_expected_payout, expires_at)
+        }
+
+        pub fn redeem_outcome_tokens(ctx: Context<RedeemOutcomeTokens>, outcome: Outcome, amount: u64) -> Result<()> {
+            instructions::redeem_outcome_tokens(ctx, outcome, amount)
+        }
 
+        pub fn initialize_share_mints(ctx: Context<InitializeShareMints>) -> Result<()> {
+            instructions::initialize_share_mints(ctx)
+        }
 
+        pub fn mint_complete_set(ctx: Context<MintCompleteSet>, amount: u64) -> Result<()> {
+            instructions::mint_complete_set(ctx, amount)
+        }
 
-    pub fn consolidate_funds(ctx: Context<ConsolidateFunds>) -> Result<()> {
-        instructions::consolidate_funds(ctx)
+        pub fn merge_complete_set(ctx: Context<BurnShares>, amount: u64) -> Result<()> {
+            instructions::merge_complete_set(ctx, amount)
+        }
+
+        pub fn redeem_winning_shares(ctx: Context<BurnShares>, amount: u64) -> Result<()> {
+            instructions::redeem_winning_shares(ctx, amount)
+        }
+
+        pub fn redeem_cancelled_shares(ctx: Context<BurnShares>, outcome: Outcome, amount: u64) -> Result<()> {
+            instructions::redeem_cancelled_shares(ctx, outcome, amount)
+        }
+
+        pub fn initialize_order_book(ctx: Context<InitializeOrderBook>, outcome: Outcome) -> Result<()> {
+            instructions::initialize_order_book(ctx, outcome)
+        }
+
+        pub fn post_order(
+            ctx: Context<TradeShares>,
+            outcome: Outcome,
+            side: OrderSide,
+            price_bps: u16,
+            quantity: u64,
+        ) -> Result<()> {
+            instructions::post_order(ctx, outcome, side, price_bps, quantity)
+        }
+
+        pub fn cancel_order(ctx: Context<TradeShares>, outcome: Outcome, order_id: u64) -> Result<()> {
+            instructions::cancel_order(ctx, outcome, order_id)
+        }
+
+        pub fn settle_trades(ctx: Context<TradeShares>, outcome: Outcome) -> Result<()> {
+            instructions::settle_trades(ctx, outcome)
+        }
+
+        pub fn sell_position(
+            ctx: Context<SellPosition>,
+            outcome: Outcome,
+            amount: u64,
+            min_proceeds: Option<u64>,
+        ) -> Result<()> {
+            instructions::sell_position(ctx, outcome, amount, min_proceeds)
+        }
+
+        pub fn transfer_position(ctx: Context<TransferPosition>, outcome: Outcome, amount: u64) -> Result<()> {
+            instructions::transfer_position(ctx, outcome, amount)
+        }
+
+        pub fn resolve_with_external_price(
+            ctx: Context<ResolveWithExternalPrice>,
+            final_price: i64,
+        ) -> Result<()> {
+            instructions::resolve_with_external_price(ctx, final_price)
+        }
+
+        pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+            instructions::claim_winnings(ctx)
+        }
+
+        pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
+            instructions::cancel_market(ctx)
+        }
+
+        pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+            instructions::claim_refund(ctx)
+        }
+
+        pub fn reclaim_bond(ctx: Context<ReclaimBond>) -> Result<()> {
+            instructions::reclaim_bond(ctx)
+        }
+
+        pub fn slash_bond(ctx: Context<SlashBond>) -> Result<()> {
+            instructions::slash_bond(ctx)
+        }
+
+        pub fn release_referral_fees(ctx: Context<ReleaseReferralFees>) -> Result<()> {
+            instructions::release_referral_fees(ctx)
+        }
+
+        pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+            instructions::close_position(ctx)
+        }
+
+        pub fn sweep_position(ctx: Context<SweepPosition>) -> Result<()> {
+            instructions::sweep_position(ctx)
+        }
+
+        // Keep old resolve_market for backwards compatibility if needed
+        pub fn resolve_market(ctx: Context<ResolveMarket>) -> Result<()> {
+            instructions::resolve_market(ctx)
+        }
+
+        pub fn initialize_market_maker(
+            ctx: Context<InitializeMarketMaker>,
+            target_spread_bps: u64,
+            max_exposure: u64,
+        ) -> Result<()> {
+            instructions::initialize_market_maker(ctx, target_spread_bps, max_exposure)
+        }
+
+        pub fn provide_liquidity(
+            ctx: Context<ProvideLiquidity>,
+            amount_per_side: u64,
+        ) -> Result<()> {
+            instructions::provide_liquidity(ctx, amount_per_side)
+        }
+
+
+
+        pub fn consolidate_funds(ctx: Context<ConsolidateFunds>) -> Result<()> {
+            instructions::consolidate_funds(ctx)
+        }
+
+        pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+            instructions::close_market(ctx)
+        }
     }
-}
\ No newline at end of file
+}
+
+pub use dispatch::*;
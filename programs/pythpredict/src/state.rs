@@ -1,6 +1,7 @@
 // programs/pythpredict/src/state.rs
 use anchor_lang::prelude::*;
 use anchor_lang::error::Error as AnchorError;
+use anchor_spl::token::spl_token::native_mint;
 use crate::errors::PredictionMarketError;
 
 // ---------- Enums ----------
@@ -11,6 +12,46 @@ pub enum Outcome {
     No = 1,
 }
 
+impl Outcome {
+    /// Seed of this side's outcome mint on tokenized markets. These tokens
+    /// are parimutuel stakes: a winning token redeems for its share of both
+    /// pools, so its value floats with the odds.
+    pub fn mint_seed(&self) -> &'static [u8] {
+        match self {
+            Outcome::Yes => b"yes_mint",
+            Outcome::No => b"no_mint",
+        }
+    }
+
+    /// Seed of this side's complete-set share mint, which pays 1:1 if the side
+    /// wins. Shares are backed by the set vault, not the pools, so they are a
+    /// different asset from the outcome mint's stake tokens and a tokenized
+    /// market with complete sets has both kinds of YES and NO token.
+    pub fn share_mint_seed(&self) -> &'static [u8] {
+        match self {
+            Outcome::Yes => b"yes_share_mint",
+            Outcome::No => b"no_share_mint",
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum OrderSide {
+    /// Buys shares with escrowed collateral.
+    Bid = 0,
+    /// Sells escrowed shares for collateral.
+    Ask = 1,
+}
+
+impl OrderSide {
+    pub fn opposite(self) -> Self {
+        match self {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
 pub enum MarketStatus {
     PendingLiquidity,
@@ -18,10 +59,14 @@ pub enum MarketStatus {
     Resolved,
     Disputed,
     Cancelled,
+    /// Betting has closed and the market is waiting for resolution. Derived
+    /// from `betting_close_time` by `Market::status_at`.
+    Closed,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Default)]
 pub enum MarketCategory {
+    #[default]
     Crypto,
     Sports,
     Politics,
@@ -30,6 +75,27 @@ pub enum MarketCategory {
     Other,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RegistryKind {
+    Category,
+    Feed,
+    Creator,
+}
+
+/// Bit flags selecting which operations a pause blocks.
+pub struct PauseScope;
+
+impl PauseScope {
+    pub const BETS: u8 = 1 << 0;
+    pub const LIQUIDITY: u8 = 1 << 1;
+    pub const CLAIMS: u8 = 1 << 2;
+    pub const ALL: u8 = Self::BETS | Self::LIQUIDITY | Self::CLAIMS;
+
+    pub fn is_valid(scope: u8) -> bool {
+        scope != 0 && scope & !Self::ALL == 0
+    }
+}
+
 // ---------- Accounts ----------
 
 #[account]
@@ -63,11 +129,49 @@ pub struct Market {
     pub emergency_paused: bool,         // 1
     pub min_liquidity: u64,             // 8
     pub liquidity_locked_until: i64,    // 8
+    pub oracle_staleness_threshold: i64, // 8
+    pub metadata_uri: [u8; 200],        // 200
+    pub metadata_hash: [u8; 32],        // 32
+    pub unclaimed_positions: u32,       // 4
+    pub pause_scope: u8,                // 1 (PauseScope bits)
+    pub funding_deadline: i64,          // 8
+    pub betting_close_time: i64,        // 8
+    pub bond_amount: u64,               // 8 (creator bond held in the bond vault)
+    pub bond_settled: bool,             // 1 (bond returned or slashed, vault closed)
+    pub exit_fee_bps: u16,              // 2 (fee on sell_position proceeds)
+    pub max_total_pot: u64,             // 8 (0 = uncapped)
+    pub max_user_stake: u64,            // 8 (0 = uncapped)
+    pub tokenized: bool,                // 1 (stakes are YES/NO mint tokens, not positions)
+    pub complete_sets: u64,             // 8 (collateral backing outstanding complete sets)
+    pub open_positions: u32,            // 4 (position accounts not yet closed, claimed or not)
+    pub settled_at: i64,                // 8 (when the market resolved or was cancelled)
+    pub registry_pages: [Pubkey; 3],    // 96 (category, feed, creator pages it is listed on)
+    pub order_books: u8,                // 1 (bit per outcome with an order book)
+    pub order_escrow: u64,              // 8 (collateral and shares owed to order book traders)
+    pub target_change_bps: i64,         // 8 (YES condition; see `resolves_yes`)
+    pub referral_escrow: u64,           // 8 (referral shares held in the fee vault)
 }
 
 impl Market {
-    // Sum(fields) = 418 → +8 discriminator = 426
-    pub const SIZE: usize = 8 + 418;
+    // Sum(fields) = 848 → +8 discriminator = 856
+    pub const SIZE: usize = 8 + 848;
+    pub const MAX_TITLE_LEN: usize = 128;
+    pub const MAX_METADATA_URI_LEN: usize = 200;
+    /// How long winners have to claim before leftover funds may be swept.
+    pub const CLAIM_SWEEP_PERIOD: i64 = 30 * 24 * 3600;
+    /// How long after settling the admin has to slash the bond before the
+    /// creator may reclaim it.
+    pub const BOND_DISPUTE_PERIOD: i64 = 3 * 24 * 3600;
+
+    /// Market title as stored in `description`.
+    pub fn title(&self) -> Result<&str> {
+        decode_fixed_str(&self.description)
+    }
+
+    /// Off-chain URI holding the full market rules.
+    pub fn metadata_uri(&self) -> Result<&str> {
+        decode_fixed_str(&self.metadata_uri)
+    }
 
     pub fn calculate_odds(&self) -> (f64, f64) {
         let total = self.yes_pool.saturating_add(self.no_pool);
@@ -87,17 +191,194 @@ impl Market {
     pub fn is_active(&self) -> bool {
         self.market_status == MarketStatus::Active
             && !self.is_resolved
-            && !self.emergency_paused
+            && !self.is_paused_for(PauseScope::BETS)
+    }
+
+    pub fn is_paused_for(&self, scope: u8) -> bool {
+        self.emergency_paused && self.pause_scope & scope != 0
+    }
+
+    pub fn is_betting_open(&self, current_time: i64) -> bool {
+        current_time < self.betting_close_time
+    }
+
+    /// Status as clients should display it: an active market past its betting
+    /// cutoff is reported as `Closed` until it resolves.
+    pub fn status_at(&self, current_time: i64) -> MarketStatus {
+        if self.market_status == MarketStatus::Active && !self.is_betting_open(current_time) {
+            MarketStatus::Closed
+        } else {
+            self.market_status
+        }
     }
 
     pub fn can_resolve(&self, current_time: i64) -> bool {
         !self.is_resolved && current_time >= self.settle_time
     }
 
+    pub fn sweep_deadline(&self) -> i64 {
+        self.settle_time.saturating_add(Self::CLAIM_SWEEP_PERIOD)
+    }
+
+    /// The creator may reclaim the bond once the market has settled and the
+    /// dispute window for slashing it has passed.
+    pub fn can_reclaim_bond(&self, current_time: i64) -> bool {
+        (self.is_resolved || self.is_cancelled())
+            && current_time >= self.settled_at.saturating_add(Self::BOND_DISPUTE_PERIOD)
+    }
+
+    /// A market can be closed once every position has been claimed, or once
+    /// the sweep deadline has passed and the remaining funds are forfeited.
+    /// Outcome token and complete-set holders cannot be counted, so markets
+    /// with either wait for the deadline. Every position account must be
+    /// closed first: a re-created market at the same address would otherwise
+    /// inherit them. Resting orders and unsettled trader balances are only
+    /// reachable through the market, so they must be withdrawn too.
+    pub fn can_close(&self, current_time: i64) -> bool {
+        let all_claimed = !self.tokenized && self.unclaimed_positions == 0 && self.complete_sets == 0;
+        (self.is_resolved || self.is_cancelled())
+            && self.open_positions == 0
+            && self.order_escrow == 0
+            && (all_claimed || current_time >= self.sweep_deadline())
+    }
+
+    /// Largest `target_change_bps` in either direction; a fall of 100% or
+    /// more could never happen.
+    pub const MAX_TARGET_CHANGE_BPS: i64 = 9_999;
+
+    pub fn validate_target_change(target_change_bps: i64) -> Result<()> {
+        require!(
+            target_change_bps.abs() <= Self::MAX_TARGET_CHANGE_BPS,
+            PredictionMarketError::InvalidParameter
+        );
+        Ok(())
+    }
+
+    /// Whether `final_price` resolves the market YES against its opening
+    /// price (`target_price`). With `target_change_bps` at 0 any move counts;
+    /// otherwise the price must rise (positive) or fall (negative) by at
+    /// least that many basis points.
+    pub fn resolves_yes(&self, final_price: i64) -> bool {
+        let opening = i128::from(self.target_price);
+        let moved = i128::from(final_price) - opening;
+        if self.target_change_bps == 0 {
+            return moved != 0;
+        }
+        let required = (opening * i128::from(self.target_change_bps.abs()) / 10_000).max(1);
+        if self.target_change_bps > 0 {
+            moved >= required
+        } else {
+            -moved >= required
+        }
+    }
+
+    pub fn has_order_book(&self, outcome: Outcome) -> bool {
+        self.order_books & (1 << outcome as u8) != 0
+    }
+
+    /// Records collateral or shares escrowed for order book traders.
+    pub fn escrow_order_funds(&mut self, amount: u64) -> Result<()> {
+        self.order_escrow = self.order_escrow
+            .checked_add(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Records escrowed collateral or shares paid back out to a trader.
+    pub fn release_order_funds(&mut self, amount: u64) -> Result<()> {
+        self.order_escrow = self.order_escrow
+            .checked_sub(amount)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Counts a newly created position account.
+    pub fn track_position(&mut self) -> Result<()> {
+        self.unclaimed_positions = self.unclaimed_positions
+            .checked_add(1)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        self.open_positions = self.open_positions
+            .checked_add(1)
+            .ok_or(PredictionMarketError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Stops counting a position account that is being closed.
+    pub fn untrack_position(&mut self, claimed: bool) {
+        if !claimed {
+            self.unclaimed_positions = self.unclaimed_positions.saturating_sub(1);
+        }
+        self.open_positions = self.open_positions.saturating_sub(1);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.market_status == MarketStatus::Cancelled
+    }
+
+    /// Collateral that `amount` complete-set shares of `side` redeem for: 1:1
+    /// on the winning side and nothing on the losing one. On a cancelled
+    /// market every share is worth half, so a YES and a NO share together
+    /// still return the unit that backed them.
+    pub fn share_value(&self, side: Outcome, amount: u64) -> Result<u64> {
+        if self.is_cancelled() {
+            return Ok(amount / 2);
+        }
+        require!(self.is_resolved, PredictionMarketError::MarketNotResolved);
+        Ok(match self.winning_outcome {
+            Some(winner) if winner == side as u8 => amount,
+            _ => 0,
+        })
+    }
+
+    /// Native SOL market: vaults hold wrapped SOL and bets/claims may use lamports directly.
+    pub fn is_native(&self) -> bool {
+        self.collateral_mint == native_mint::ID
+    }
+
     pub fn has_minimum_liquidity(&self) -> bool {
         self.get_total_pot() >= self.min_liquidity
     }
 
+    /// Moves a `PendingLiquidity` market to `Active` once it is funded.
+    pub fn activate_if_funded(&mut self) -> bool {
+        if self.market_status == MarketStatus::PendingLiquidity && self.has_minimum_liquidity() {
+            self.market_status = MarketStatus::Active;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_funding_expired(&self, current_time: i64) -> bool {
+        self.market_status == MarketStatus::PendingLiquidity
+            && current_time >= self.funding_deadline
+    }
+
+    /// Stakes may only change hands while the outcome is still open: not once
+    /// the market has resolved, been cancelled or missed its funding deadline.
+    pub fn can_transfer_positions(&self, current_time: i64) -> bool {
+        !self.is_resolved && !self.is_cancelled() && !self.is_funding_expired(current_time)
+    }
+
+    /// Markets still taking bets or waiting on liquidity may be cancelled.
+    pub fn can_cancel(&self) -> bool {
+        !self.is_resolved
+            && matches!(self.market_status, MarketStatus::Active | MarketStatus::PendingLiquidity)
+    }
+
+    /// Cancels a market that missed its funding deadline so positions can be refunded.
+    pub fn cancel_if_funding_expired(&mut self, current_time: i64) -> bool {
+        if self.is_funding_expired(current_time) {
+            self.cancel(current_time);
+            return true;
+        }
+        false
+    }
+
+    pub fn cancel(&mut self, current_time: i64) {
+        self.market_status = MarketStatus::Cancelled;
+        self.settled_at = current_time;
+    }
+
     pub fn validate_bet_amount(&self, amount: u64) -> Result<()> {
         require!(amount >= self.min_bet_amount, PredictionMarketError::BetTooSmall);
         require!(amount <= self.max_bet_amount, PredictionMarketError::BetTooLarge);
@@ -115,7 +396,7 @@ impl Market {
             PredictionMarketError::PriceConfidenceTooHigh
         );
         require!(
-            current_time - last_update <= 60,
+            current_time - last_update <= self.oracle_staleness_threshold,
             PredictionMarketError::PriceTooStale
         );
         Ok(())
@@ -135,7 +416,7 @@ impl Market {
     /// Simple price impact approximation in bps for adding `amount` to one side.
 pub fn calculate_price_impact(&self, amount: u64, side: Outcome) -> Result<u64> {
     let (mut y, mut n) = (self.yes_pool as u128, self.no_pool as u128);
-    let before = if y + n == 0 { 5_000u128 } else { y * 10_000 / (y + n) };
+    let before = (y * 10_000).checked_div(y + n).unwrap_or(5_000u128);
 
     match side {
         Outcome::Yes => {
@@ -147,7 +428,7 @@ pub fn calculate_price_impact(&self, amount: u64, side: Outcome) -> Result<u64>
     }
 
     let after = (y * 10_000) / (y + n);
-    let impact = if after > before { after - before } else { before - after };
+    let impact = after.abs_diff(before);
 
     // ↓ Concrete AnchorError, no type inference ambiguity
     let val: u64 = u64::try_from(impact).map_err(|_| overflow_err())?;
@@ -187,6 +468,104 @@ pub fn calculate_price_impact(&self, amount: u64, side: Outcome) -> Result<u64>
             .checked_div(win_pool.checked_add(amount).ok_or_else(overflow_err)?)
             .ok_or_else(overflow_err)
     }
+
+    /// What `amount` of `side` stake sells back for, before the exit fee: its
+    /// payout if `side` wins, weighted by the side's implied probability
+    /// averaged over the withdrawal, from the current pools down to the pools
+    /// without the stake. That works out to
+    /// `amount - amount² * other / (2 * side * (side + other - amount))`, so a
+    /// sale never returns more than the stake and large exits or odds that
+    /// moved against `side` sell at a discount. Rounds down in the vaults' favour.
+    pub fn get_exit_value(&self, amount: u64, side: Outcome) -> Result<u64> {
+        let (side_pool, other_pool) = match side {
+            Outcome::Yes => (self.yes_pool, self.no_pool),
+            Outcome::No => (self.no_pool, self.yes_pool),
+        };
+        require!(amount <= side_pool, PredictionMarketError::InsufficientLiquidity);
+        if amount == 0 || other_pool == 0 {
+            return Ok(amount);
+        }
+
+        // side + other - amount >= other > 0, and each step stays within u128
+        let remaining_pot = (side_pool as u128) + (other_pool as u128) - (amount as u128);
+        let discount = ((amount as u128) * (other_pool as u128))
+            .div_ceil(remaining_pot)
+            .checked_mul(amount as u128)
+            .ok_or_else(overflow_err)?
+            .div_ceil(2 * side_pool as u128);
+        Ok(amount.saturating_sub(u64::try_from(discount).unwrap_or(u64::MAX)))
+    }
+
+    /// Fails if adding `amount` to the pools would exceed `max_total_pot`.
+    pub fn check_capacity(&self, amount: u64) -> Result<()> {
+        if self.max_total_pot > 0 {
+            let pot_after = self.get_total_pot()
+                .checked_add(amount)
+                .ok_or_else(overflow_err)?;
+            require!(
+                pot_after <= self.max_total_pot,
+                PredictionMarketError::MarketCapacityReached
+            );
+        }
+        Ok(())
+    }
+
+    /// Fails if a user holding `current_stake` would exceed `max_user_stake` by adding `amount`.
+    pub fn check_user_stake(&self, current_stake: u64, amount: u64) -> Result<()> {
+        if self.max_user_stake > 0 {
+            let stake_after = current_stake.checked_add(amount).ok_or_else(overflow_err)?;
+            require!(
+                stake_after <= self.max_user_stake,
+                PredictionMarketError::UserStakeLimitExceeded
+            );
+        }
+        Ok(())
+    }
+
+    /// Slippage guard for a bet: `stake` is the post-fee amount about to be
+    /// added to `side`, checked against the pools before it lands.
+    pub fn check_min_payout(&self, stake: u64, side: Outcome, min_expected_payout: Option<u64>) -> Result<()> {
+        if let Some(min_payout) = min_expected_payout {
+            require!(
+                self.get_expected_payout(stake, side)? >= min_payout,
+                PredictionMarketError::SlippageExceeded
+            );
+        }
+        Ok(())
+    }
+}
+
+/// What a slice of stake cost: the amount put in for it, and the part of
+/// that which went to the fee vault.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakeShare {
+    pub invested: u64,
+    pub fees_paid: u64,
+}
+
+/// What one bet actually put into program accounts once any transfer fee
+/// was withheld: the stake in its pool, the protocol fee in the fee vault
+/// and the referrer's cut, escrowed in the fee vault for positions and paid
+/// to the referral vault for outcome tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LandedBet {
+    pub stake: u64,
+    pub protocol_fee: u64,
+    pub referral_fee: u64,
+}
+impl LandedBet {
+    /// Everything the bet delivered; recorded as volume and as invested.
+    pub fn total(&self) -> Result<u64> {
+        self.stake
+            .checked_add(self.protocol_fee)
+            .and_then(|sum| sum.checked_add(self.referral_fee))
+            .ok_or_else(overflow_err)
+    }
+
+    /// Cost basis for the position; only the protocol fee is refundable.
+    pub fn stake_share(&self) -> Result<StakeShare> {
+        Ok(StakeShare { invested: self.total()?, fees_paid: self.protocol_fee })
+    }
 }
 
 #[account]
@@ -202,14 +581,87 @@ pub struct Position {
     pub bet_timestamp: i64,     // 8
     pub total_invested: u64,    // 8
     pub pending_payout: u64,    // 8
+    pub fees_paid: u64,         // 8
+    pub referral_fees: u64,     // 8 (referrer's share escrowed in the fee vault)
 }
 impl Position {
-    // Sum(fields) = 121 → +8 discriminator = 129
-    pub const SIZE: usize = 8 + 121;
+    // Sum(fields) = 137 → +8 discriminator = 145
+    pub const SIZE: usize = 8 + 137;
 
     pub fn get_total_stake(&self) -> u64 { self.yes_amount.saturating_add(self.no_amount) }
     pub fn has_position(&self) -> bool { self.yes_amount > 0 || self.no_amount > 0 }
 
+    /// Nothing is left to pay out: already claimed, emptied, or a confirmed loss.
+    pub fn is_settled(&self, market: &Market) -> bool {
+        if self.claimed || !self.has_position() {
+            return true;
+        }
+        match market.winning_outcome {
+            Some(winner) if market.is_resolved => self.get_winning_stake(winner) == 0,
+            _ => false,
+        }
+    }
+
+    /// Protocol fee this position put into the fee vault, returned alongside
+    /// the stake on a cancelled market. The escrowed referral share is kept
+    /// separately in `referral_fees`.
+    pub fn get_fees_paid(&self) -> u64 {
+        self.fees_paid
+    }
+
+    /// Removes `amount` of `side` stake along with its share of
+    /// `total_invested` and `fees_paid`, so both stay proportional. Returns
+    /// the removed shares.
+    pub fn reduce_stake(&mut self, side: Outcome, amount: u64) -> Result<StakeShare> {
+        let total_stake = self.get_total_stake();
+        let stake = match side {
+            Outcome::Yes => &mut self.yes_amount,
+            Outcome::No => &mut self.no_amount,
+        };
+        require!(amount > 0 && amount <= *stake, PredictionMarketError::InsufficientBalance);
+        *stake -= amount;
+
+        let pro_rata = |value: u64| -> Result<u64> {
+            let part = (value as u128)
+                .checked_mul(amount as u128)
+                .ok_or_else(overflow_err)?
+                / total_stake as u128;
+            Ok((part as u64).min(value))
+        };
+        let share = StakeShare {
+            invested: pro_rata(self.total_invested)?,
+            fees_paid: pro_rata(self.fees_paid)?,
+        };
+        self.total_invested -= share.invested;
+        self.fees_paid -= share.fees_paid;
+        Ok(share)
+    }
+
+    /// Credits `amount` of `side` stake along with what it cost.
+    pub fn add_stake(&mut self, side: Outcome, amount: u64, share: StakeShare) -> Result<()> {
+        let stake = match side {
+            Outcome::Yes => &mut self.yes_amount,
+            Outcome::No => &mut self.no_amount,
+        };
+        *stake = stake.checked_add(amount).ok_or_else(overflow_err)?;
+        self.total_invested = self.total_invested.checked_add(share.invested).ok_or_else(overflow_err)?;
+        self.fees_paid = self.fees_paid.checked_add(share.fees_paid).ok_or_else(overflow_err)?;
+        Ok(())
+    }
+
+    /// Whether the position counts against its owner's `open_positions`: it
+    /// has stake that has not been claimed.
+    pub fn holds_slot(&self) -> bool {
+        !self.claimed && self.has_position()
+    }
+
+    /// The escrowed referral share can be paid to the referrer once it can no
+    /// longer be refunded: the market resolved, or the stake it came with has
+    /// been sold or transferred away.
+    pub fn can_release_referral_fees(&self, market: &Market) -> bool {
+        self.referral_fees > 0 && (market.is_resolved || !self.has_position())
+    }
+
     pub fn get_winning_stake(&self, winning_outcome: u8) -> u64 {
         match winning_outcome {
             0 => self.yes_amount,
@@ -255,6 +707,628 @@ impl MarketMaker {
     pub const SIZE: usize = 8 + 113;
 }
 
+#[account]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,                  // 32
+    pub pauser: Pubkey,                 // 32
+    pub global_paused: bool,            // 1
+    pub global_pause_scope: u8,         // 1
+    pub bump: u8,                       // 1
+    pub max_open_positions: u32,        // 4 (per user across all markets, 0 = unlimited)
+    pub referral_share_bps: u16,        // 2 (share of each bet fee paid to the referrer)
+}
+impl ProtocolConfig {
+    // Sum(fields) = 73 → +8 discriminator = 81
+    pub const SIZE: usize = 8 + 73;
+
+    /// Largest share of a fee that may go to referrers.
+    pub const MAX_REFERRAL_SHARE_BPS: u16 = 5000;
+
+    /// Splits `fee` into (referrer share, remainder for the fee vault).
+    pub fn split_referral_fee(&self, fee: u64) -> Result<(u64, u64)> {
+        let referral_fee = (fee as u128)
+            .checked_mul(self.referral_share_bps as u128)
+            .ok_or_else(overflow_err)?
+            / 10_000;
+        let referral_fee = referral_fee as u64;
+        Ok((referral_fee, fee - referral_fee))
+    }
+
+    pub fn is_pause_authority(&self, key: &Pubkey) -> bool {
+        *key == self.pauser || *key == self.admin
+    }
+
+    pub fn is_paused_for(&self, scope: u8) -> bool {
+        self.global_paused && self.global_pause_scope & scope != 0
+    }
+
+    /// Fails if either the protocol-wide or the market-level pause covers `scope`.
+    pub fn require_not_paused(&self, market: &Market, scope: u8) -> Result<()> {
+        require!(!self.is_paused_for(scope), PredictionMarketError::ProtocolPaused);
+        require!(!market.is_paused_for(scope), PredictionMarketError::MarketPaused);
+        Ok(())
+    }
+}
+
+#[account]
+pub struct MarketTemplate {
+    pub template_id: u64,               // 8
+    pub admin: Pubkey,                  // 32
+    pub pyth_feed: Pubkey,              // 32
+    pub collateral_mint: Pubkey,        // 32
+    pub resolver_authority: Pubkey,     // 32
+    pub duration: i64,                  // 8
+    pub fee_bps: u16,                   // 2
+    pub min_bet_amount: u64,            // 8
+    pub max_bet_amount: u64,            // 8
+    pub betting_cutoff_secs: i64,       // 8
+    pub category: MarketCategory,       // 1
+    pub target_change_bps: i64,         // 8
+    pub is_active: bool,                // 1
+    pub markets_created: u64,           // 8
+    pub bump: u8,                       // 1
+}
+impl MarketTemplate {
+    // Sum(fields) = 227 → +8 discriminator = 235
+    pub const SIZE: usize = 8 + 227;
+
+    /// Market settings every instance of this template is created with.
+    pub fn market_config(&self) -> MarketConfig {
+        MarketConfig {
+            fee_bps: self.fee_bps,
+            min_bet_amount: self.min_bet_amount,
+            max_bet_amount: self.max_bet_amount,
+            betting_cutoff_secs: self.betting_cutoff_secs,
+            category: self.category,
+            ..MarketConfig::default()
+        }
+    }
+}
+
+/// Admin-chosen settings for `create_market_template`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct MarketTemplateParams {
+    pub duration: i64,
+    pub fee_bps: u16,
+    pub min_bet_amount: u64,
+    pub max_bet_amount: u64,
+    pub betting_cutoff_secs: i64,
+    pub category: MarketCategory,
+    /// Move from the opening price that resolves YES, as `Market::target_change_bps`.
+    pub target_change_bps: i64,
+}
+
+impl MarketTemplateParams {
+    pub fn validate(&self, bounds: &MarketParams) -> Result<()> {
+        Market::validate_target_change(self.target_change_bps)?;
+        require!(
+            self.duration > bounds.min_settlement_time && self.duration < bounds.max_settlement_time,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.betting_cutoff_secs >= 0 && self.betting_cutoff_secs < self.duration,
+            PredictionMarketError::InvalidParameter
+        );
+        MarketConfig {
+            fee_bps: self.fee_bps,
+            min_bet_amount: self.min_bet_amount,
+            max_bet_amount: self.max_bet_amount,
+            ..MarketConfig::default()
+        }
+        .validate(bounds)
+    }
+}
+
+/// Head of a paged market index (one per category, feed and creator).
+/// Entries live in `MarketRegistryPage`s; page `n` holds markets
+/// `n * PAGE_SIZE .. (n + 1) * PAGE_SIZE` in creation order.
+#[account]
+pub struct MarketRegistry {
+    pub kind: RegistryKind,             // 1
+    pub market_count: u64,              // 8
+    pub bump: u8,                       // 1
+}
+impl MarketRegistry {
+    // Sum(fields) = 10 → +8 discriminator = 18
+    pub const SIZE: usize = 8 + 10;
+
+    /// Page the next registered market will be written to.
+    pub fn current_page(&self) -> u32 {
+        (self.market_count / MarketRegistryPage::PAGE_SIZE as u64) as u32
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RegistryEntry {
+    pub market: Pubkey,                 // 32
+    pub status: MarketStatus,           // 1
+}
+
+#[account]
+pub struct MarketRegistryPage {
+    pub registry: Pubkey,               // 32
+    pub page_index: u32,                // 4
+    pub entries: Vec<RegistryEntry>,    // 4 + 33 * PAGE_SIZE
+}
+impl MarketRegistryPage {
+    pub const PAGE_SIZE: usize = 32;
+    // Sum(fields) = 32 + 4 + 4 + 33 * 32 = 1096 → +8 discriminator = 1104
+    pub const SIZE: usize = 8 + 32 + 4 + 4 + 33 * Self::PAGE_SIZE;
+
+    pub fn push(&mut self, entry: RegistryEntry) -> Result<()> {
+        require!(
+            self.entries.len() < Self::PAGE_SIZE,
+            PredictionMarketError::RegistryPageFull
+        );
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Updates the entry for `market`; returns false if it is not on this page.
+    pub fn set_status(&mut self, market: &Pubkey, status: MarketStatus) -> bool {
+        match self.entries.iter_mut().find(|e| e.market == *market) {
+            Some(entry) => {
+                entry.status = status;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops a closed market from the page; returns false if it is not on this page.
+    pub fn remove(&mut self, market: &Pubkey) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.market != *market);
+        self.entries.len() != before
+    }
+}
+
+/// High bit set on every counter-assigned market nonce. Explicit nonces must
+/// leave it clear, so the two never collide on the same market PDA.
+pub const COUNTER_NONCE_FLAG: u64 = 1 << 63;
+
+/// Per-creator market counter. `initialize_market` without an explicit nonce
+/// uses `COUNTER_NONCE_FLAG | market_count`, so a creator's counter markets
+/// are enumerable by walking `0..market_count`.
+#[account]
+pub struct CreatorProfile {
+    pub creator: Pubkey,                // 32
+    pub market_count: u64,              // 8
+    pub bump: u8,                       // 1
+}
+impl CreatorProfile {
+    // Sum(fields) = 41 → +8 discriminator = 49
+    pub const SIZE: usize = 8 + 41;
+
+    /// Nonce the market PDA is derived from: the explicit one if given,
+    /// otherwise the next counter nonce.
+    pub fn resolve_nonce(&self, explicit: Option<u64>) -> u64 {
+        explicit.unwrap_or(COUNTER_NONCE_FLAG | self.market_count)
+    }
+
+    /// Returns the nonce for the next market and advances the counter.
+    pub fn next_nonce(&mut self) -> Result<u64> {
+        let nonce = self.resolve_nonce(None);
+        self.market_count = self.market_count.checked_add(1).ok_or_else(overflow_err)?;
+        Ok(nonce)
+    }
+}
+
+/// One bet: a whole `place_bet`, or one leg of `place_bets` whose market,
+/// position and vault accounts come from `remaining_accounts`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct BetOrder {
+    pub outcome: Outcome,
+    pub amount: u64,
+    pub min_expected_payout: Option<u64>,
+}
+impl BetOrder {
+    pub const MAX_BATCH_SIZE: usize = 10;
+    /// market, position, YES vault, NO vault, fee vault
+    pub const ACCOUNTS_PER_BET: usize = 5;
+
+    /// Checks a `place_bets` batch against the remaining accounts passed with
+    /// it before any leg moves funds.
+    pub fn validate_batch(bets: &[BetOrder], account_count: usize) -> Result<()> {
+        require!(
+            !bets.is_empty() && bets.len() <= Self::MAX_BATCH_SIZE,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            account_count == bets.len() * Self::ACCOUNTS_PER_BET,
+            PredictionMarketError::InvalidBatchAccount
+        );
+        require!(
+            bets.iter().all(|bet| bet.amount > 0),
+            PredictionMarketError::InvalidAmount
+        );
+        Ok(())
+    }
+}
+
+/// Per-user counters shared by every market.
+#[account]
+pub struct UserStats {
+    pub user: Pubkey,                   // 32
+    pub open_positions: u32,            // 4 (positions not yet claimed, refunded or closed)
+    pub bump: u8,                       // 1
+    pub bet_count: u64,                 // 8
+    pub referrer: Option<Pubkey>,       // 1 + 32 (fixed by the user's first bet)
+}
+impl UserStats {
+    // Sum(fields) = 78 → +8 discriminator = 86
+    pub const SIZE: usize = 8 + 78;
+
+    /// Counts a newly opened position; `max_open_positions` of 0 means unlimited.
+    pub fn open_position(&mut self, max_open_positions: u32) -> Result<()> {
+        require!(
+            max_open_positions == 0 || self.open_positions < max_open_positions,
+            PredictionMarketError::TooManyPositions
+        );
+        self.open_positions = self.open_positions.checked_add(1).ok_or_else(overflow_err)?;
+        Ok(())
+    }
+
+    pub fn close_position(&mut self) {
+        self.open_positions = self.open_positions.saturating_sub(1);
+    }
+
+    /// Checks the referrer passed with a bet and returns the one on record.
+    /// The first bet decides attribution; later bets may omit the referrer
+    /// but cannot name a different one.
+    pub fn attribute_referrer(&mut self, referrer: Option<Pubkey>) -> Result<Option<Pubkey>> {
+        if self.bet_count == 0 {
+            require!(referrer != Some(self.user), PredictionMarketError::InvalidReferrer);
+            self.referrer = referrer;
+        } else if referrer.is_some() {
+            require!(referrer == self.referrer, PredictionMarketError::InvalidReferrer);
+        }
+        self.bet_count = self.bet_count.checked_add(1).ok_or_else(overflow_err)?;
+        Ok(self.referrer)
+    }
+}
+
+/// Referral account for a wallet. Its share of bet fees accrues in one
+/// `["referral_vault", referrer, mint]` token account per collateral mint.
+#[account]
+pub struct Referrer {
+    pub wallet: Pubkey,                 // 32
+    pub referred_users: u32,            // 4
+    pub referred_volume: u64,           // 8 (gross bet amounts, in each mint's base units)
+    pub fees_earned: u64,               // 8
+    pub bump: u8,                       // 1
+}
+impl Referrer {
+    // Sum(fields) = 53 → +8 discriminator = 61
+    pub const SIZE: usize = 8 + 53;
+
+    pub fn record_bet(&mut self, amount: u64, fee: u64) -> Result<()> {
+        self.referred_volume = self.referred_volume.checked_add(amount).ok_or_else(overflow_err)?;
+        self.fees_earned = self.fees_earned.checked_add(fee).ok_or_else(overflow_err)?;
+        Ok(())
+    }
+}
+
+/// Session key a user has authorized to bet and claim on their behalf. The
+/// delegate PDA is also the SPL delegate on the user's token account, so bets
+/// are paid from the user's funds without their signature.
+#[account]
+pub struct BettingDelegate {
+    pub user: Pubkey,                   // 32
+    pub delegate: Pubkey,               // 32
+    pub total_cap: u64,                 // 8 (lifetime spend allowed)
+    pub per_bet_cap: u64,               // 8
+    pub spent: u64,                     // 8
+    pub expires_at: i64,                // 8
+    pub bump: u8,                       // 1
+}
+impl BettingDelegate {
+    // Sum(fields) = 97 → +8 discriminator = 105
+    pub const SIZE: usize = 8 + 97;
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.total_cap.saturating_sub(self.spent)
+    }
+
+    /// Checks a bet of `amount` against the expiry and both caps, then records it.
+    pub fn record_spend(&mut self, amount: u64, now: i64) -> Result<()> {
+        require!(!self.is_expired(now), PredictionMarketError::DelegateExpired);
+        require!(
+            amount <= self.per_bet_cap && amount <= self.remaining(),
+            PredictionMarketError::DelegateLimitExceeded
+        );
+        self.spent = self.spent.checked_add(amount).ok_or_else(overflow_err)?;
+        Ok(())
+    }
+}
+
+/// Resting limit order. `escrow` is what backs the unfilled quantity:
+/// collateral for bids, shares for asks.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Order {
+    pub id: u64,                        // 8
+    pub owner: Pubkey,                  // 32
+    pub price_bps: u16,                 // 2 (collateral per share, 10_000 = 1:1)
+    pub quantity: u64,                  // 8 (shares still open)
+    pub escrow: u64,                    // 8
+}
+impl Order {
+    pub const SIZE: usize = 58;
+
+    /// Returns the order's escrow to its owner, as when it is evicted.
+    pub fn escrow_credit(&self, side: OrderSide) -> Credit {
+        match side {
+            OrderSide::Bid => Credit { owner: self.owner, shares: 0, collateral: self.escrow },
+            OrderSide::Ask => Credit { owner: self.owner, shares: self.escrow, collateral: 0 },
+        }
+    }
+}
+
+/// Shares and collateral owed to a maker, paid into its `TraderBalance`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Credit {
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub collateral: u64,
+}
+
+/// Totals of the fills an incoming order took from the book.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatchResult {
+    /// Shares traded.
+    pub filled: u64,
+    /// Collateral paid for them at the resting orders' prices, rounded
+    /// against the taker.
+    pub cost: u64,
+    /// What each filled maker is owed, one entry per owner.
+    pub credits: Vec<Credit>,
+}
+impl MatchResult {
+    /// Adds `credit` to its owner's entry.
+    pub fn credit(&mut self, credit: Credit) -> Result<()> {
+        match self.credits.iter_mut().find(|entry| entry.owner == credit.owner) {
+            Some(entry) => {
+                entry.shares = entry.shares.checked_add(credit.shares).ok_or_else(overflow_err)?;
+                entry.collateral = entry.collateral.checked_add(credit.collateral).ok_or_else(overflow_err)?;
+            }
+            None => self.credits.push(credit),
+        }
+        Ok(())
+    }
+}
+
+/// Limit order book for one outcome's complete-set shares. Bids are kept
+/// best (highest) price first and asks lowest first, oldest first within a
+/// price. Fills are credited to the makers' `TraderBalance` accounts, so a
+/// filled order leaves the book at once; when a side is full, a better-priced
+/// order evicts the worst one.
+#[account]
+pub struct OrderBook {
+    pub market: Pubkey,                 // 32
+    pub outcome: u8,                    // 1
+    pub next_order_id: u64,             // 8
+    pub bump: u8,                       // 1
+    pub bids: Vec<Order>,               // 4 + 58 * MAX_ORDERS
+    pub asks: Vec<Order>,               // 4 + 58 * MAX_ORDERS
+}
+impl OrderBook {
+    pub const MAX_ORDERS: usize = 32;
+    // Sum(fields) = 42 + 2 * (4 + 58 * 32) = 3762 → +8 discriminator = 3770
+    pub const SIZE: usize = 8 + 42 + 2 * (4 + Order::SIZE * Self::MAX_ORDERS);
+
+    /// Collateral that backs `quantity` shares at `price_bps`, rounded down.
+    pub fn cost(quantity: u64, price_bps: u16) -> Result<u64> {
+        let cost = (quantity as u128)
+            .checked_mul(price_bps as u128)
+            .ok_or_else(overflow_err)?
+            / 10_000;
+        u64::try_from(cost).map_err(|_| overflow_err())
+    }
+
+    /// Same as `cost`, rounded up: what a taker buying shares pays.
+    pub fn cost_rounded_up(quantity: u64, price_bps: u16) -> Result<u64> {
+        let cost = (quantity as u128)
+            .checked_mul(price_bps as u128)
+            .ok_or_else(overflow_err)?
+            .div_ceil(10_000);
+        u64::try_from(cost).map_err(|_| overflow_err())
+    }
+
+    /// Fills an incoming `side` order against the opposite side of the book,
+    /// best price first, for as long as the prices cross. Makers' earnings,
+    /// plus any escrow dust left on an order that filled completely, are
+    /// returned as credits and filled orders are removed; the caller moves
+    /// the taker's funds.
+    pub fn match_order(&mut self, side: OrderSide, price_bps: u16, quantity: u64) -> Result<MatchResult> {
+        let mut result = MatchResult::default();
+        let resting = match side {
+            OrderSide::Bid => &mut self.asks,
+            OrderSide::Ask => &mut self.bids,
+        };
+
+        for maker in resting.iter_mut() {
+            let remaining = quantity - result.filled;
+            if remaining == 0 {
+                break;
+            }
+            let crosses = match side {
+                OrderSide::Bid => maker.price_bps <= price_bps,
+                OrderSide::Ask => maker.price_bps >= price_bps,
+            };
+            if !crosses {
+                break;
+            }
+
+            // Rounding always favours the maker: a buying taker pays up, a
+            // selling taker is paid out of the bid's escrow rounded down
+            let fill = remaining.min(maker.quantity);
+            let cost = match side {
+                OrderSide::Bid => Self::cost_rounded_up(fill, maker.price_bps)?,
+                OrderSide::Ask => Self::cost(fill, maker.price_bps)?,
+            };
+            maker.quantity -= fill;
+            let mut credit = match side {
+                // Maker sold shares out of escrow for collateral
+                OrderSide::Bid => {
+                    maker.escrow = maker.escrow.checked_sub(fill).ok_or_else(overflow_err)?;
+                    Credit { owner: maker.owner, shares: 0, collateral: cost }
+                }
+                // Maker bought shares with escrowed collateral
+                OrderSide::Ask => {
+                    maker.escrow = maker.escrow.checked_sub(cost).ok_or_else(overflow_err)?;
+                    Credit { owner: maker.owner, shares: fill, collateral: 0 }
+                }
+            };
+            if maker.quantity == 0 {
+                let dust = maker.escrow_credit(side.opposite());
+                credit.shares += dust.shares;
+                credit.collateral += dust.collateral;
+                maker.escrow = 0;
+            }
+            result.credit(credit)?;
+
+            result.filled += fill;
+            result.cost = result.cost.checked_add(cost).ok_or_else(overflow_err)?;
+        }
+        resting.retain(|order| order.quantity > 0);
+
+        Ok(result)
+    }
+
+    /// Rests an order behind every order at the same or a better price. On a
+    /// full side the order must beat the worst price there, which it evicts;
+    /// the evicted order is returned so its escrow can be credited back.
+    pub fn insert(
+        &mut self,
+        side: OrderSide,
+        owner: Pubkey,
+        price_bps: u16,
+        quantity: u64,
+        escrow: u64,
+    ) -> Result<(u64, Option<Order>)> {
+        let orders = match side {
+            OrderSide::Bid => &mut self.bids,
+            OrderSide::Ask => &mut self.asks,
+        };
+        let evicted = if orders.len() >= Self::MAX_ORDERS {
+            let beats_worst = orders.last().is_some_and(|worst| match side {
+                OrderSide::Bid => price_bps > worst.price_bps,
+                OrderSide::Ask => price_bps < worst.price_bps,
+            });
+            require!(beats_worst, PredictionMarketError::OrderBookFull);
+            orders.pop()
+        } else {
+            None
+        };
+
+        let id = self.next_order_id;
+        self.next_order_id = id.checked_add(1).ok_or_else(overflow_err)?;
+
+        let index = orders.partition_point(|order| match side {
+            OrderSide::Bid => order.price_bps >= price_bps,
+            OrderSide::Ask => order.price_bps <= price_bps,
+        });
+        orders.insert(index, Order { id, owner, price_bps, quantity, escrow });
+        Ok((id, evicted))
+    }
+
+    /// Takes `owner`'s order `id` off the book.
+    pub fn remove(&mut self, id: u64, owner: &Pubkey) -> Result<(OrderSide, Order)> {
+        for (side, orders) in [(OrderSide::Bid, &mut self.bids), (OrderSide::Ask, &mut self.asks)] {
+            if let Some(index) = orders.iter().position(|order| order.id == id) {
+                require_keys_eq!(orders[index].owner, *owner, PredictionMarketError::Unauthorized);
+                return Ok((side, orders.remove(index)));
+            }
+        }
+        err!(PredictionMarketError::OrderNotFound)
+    }
+}
+
+/// Shares and collateral a trader has earned on one order book from fills
+/// and evictions, withdrawn with `settle_trades`.
+#[account]
+pub struct TraderBalance {
+    pub order_book: Pubkey,             // 32
+    pub owner: Pubkey,                  // 32
+    pub shares: u64,                    // 8
+    pub collateral: u64,                // 8
+    pub bump: u8,                       // 1
+}
+impl TraderBalance {
+    // Sum(fields) = 81 → +8 discriminator = 89
+    pub const SIZE: usize = 8 + 81;
+
+    pub fn add(&mut self, credit: &Credit) -> Result<()> {
+        self.shares = self.shares.checked_add(credit.shares).ok_or_else(overflow_err)?;
+        self.collateral = self.collateral.checked_add(credit.collateral).ok_or_else(overflow_err)?;
+        Ok(())
+    }
+}
+
+/// Admin-managed allowlist entry for a collateral mint. Markets can only be
+/// created for mints with an enabled config; bet limits are in the mint's base units.
+#[account]
+pub struct CollateralConfig {
+    pub mint: Pubkey,                   // 32
+    pub decimals: u8,                   // 1
+    pub min_bet_amount: u64,            // 8
+    pub max_bet_amount: u64,            // 8
+    pub fee_bps_override: Option<u16>,  // 1 + 2
+    pub enabled: bool,                  // 1
+    pub creator_bond: u64,              // 8
+    pub bump: u8,                       // 1
+}
+impl CollateralConfig {
+    // Sum(fields) = 62 → +8 discriminator = 70
+    pub const SIZE: usize = 8 + 62;
+
+    /// Protocol bounds scaled to this mint's decimals, narrowed to its bet limits.
+    pub fn bounds(&self) -> Result<MarketParams> {
+        let scaled = MarketParams::for_decimals(self.decimals)?;
+        Ok(MarketParams {
+            min_bet_amount: self.min_bet_amount,
+            max_bet_amount: self.max_bet_amount,
+            ..scaled
+        })
+    }
+
+    /// Fee a new market pays: the mint's override if set, otherwise the creator's choice.
+    pub fn fee_bps(&self, requested: u16) -> u16 {
+        self.fee_bps_override.unwrap_or(requested)
+    }
+}
+
+/// Settings passed to `set_collateral_config`; decimals are read from the mint.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct CollateralConfigParams {
+    pub min_bet_amount: u64,
+    pub max_bet_amount: u64,
+    pub fee_bps_override: Option<u16>,
+    pub enabled: bool,
+    /// Bond every market creator posts in this mint, returned on clean resolution.
+    pub creator_bond: u64,
+}
+
+impl CollateralConfigParams {
+    pub fn validate(&self, decimals: u8) -> Result<()> {
+        let bounds = MarketParams::for_decimals(decimals)?;
+        require!(
+            self.min_bet_amount >= bounds.min_bet_amount
+                && self.max_bet_amount <= bounds.max_bet_amount
+                && self.min_bet_amount <= self.max_bet_amount,
+            PredictionMarketError::InvalidParameter
+        );
+        if let Some(fee_bps) = self.fee_bps_override {
+            require!(fee_bps <= bounds.max_fee_bps, PredictionMarketError::InvalidParameter);
+        }
+        Ok(())
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub struct FeeDistribution {
     pub treasury_bps: u16,
@@ -276,6 +1350,7 @@ impl FeeCollector {
 
 // ---------- Params ----------
 
+/// Protocol-wide bounds that every market's configuration is validated against.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
 pub struct MarketParams {
     pub min_bet_amount: u64,
@@ -285,6 +1360,39 @@ pub struct MarketParams {
     pub max_price_confidence: u64,
     pub min_liquidity: u64,
     pub oracle_staleness_threshold: i64,
+    pub max_fee_bps: u16,
+    pub max_min_liquidity: u64,
+    /// Upper bound on `MarketConfig::oracle_confidence`, in basis points of price.
+    pub max_oracle_confidence_bps: u64,
+}
+
+impl MarketParams {
+    /// Decimals the token amounts in `default()` are expressed in.
+    pub const BASE_DECIMALS: u8 = 6;
+
+    /// Default bounds with every token amount rescaled from `BASE_DECIMALS` to `decimals`.
+    pub fn for_decimals(decimals: u8) -> Result<Self> {
+        let base = Self::default();
+        let scale = |amount: u64| -> Result<u64> {
+            if decimals >= Self::BASE_DECIMALS {
+                let factor = 10u64
+                    .checked_pow(u32::from(decimals - Self::BASE_DECIMALS))
+                    .ok_or_else(overflow_err)?;
+                amount.checked_mul(factor).ok_or_else(overflow_err)
+            } else {
+                let divisor = 10u64.pow(u32::from(Self::BASE_DECIMALS - decimals));
+                Ok((amount / divisor).max(1))
+            }
+        };
+
+        Ok(Self {
+            min_bet_amount: scale(base.min_bet_amount)?,
+            max_bet_amount: scale(base.max_bet_amount)?,
+            min_liquidity: scale(base.min_liquidity)?,
+            max_min_liquidity: scale(base.max_min_liquidity)?,
+            ..base
+        })
+    }
 }
 
 impl Default for MarketParams {
@@ -297,10 +1405,159 @@ impl Default for MarketParams {
             max_price_confidence: 5,               // 0.5%
             min_liquidity: 10_000_000,             // 10 tokens
             oracle_staleness_threshold: 60,        // 60s
+            max_fee_bps: 1_000,                    // 10%
+            max_min_liquidity: 1_000_000_000_000,  // 1M tokens
+            max_oracle_confidence_bps: 500,        // 5%
         }
     }
 }
 
+/// Per-market settings chosen by the creator at `initialize_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MarketConfig {
+    pub fee_bps: u16,
+    pub min_bet_amount: u64,
+    pub max_bet_amount: u64,
+    pub min_liquidity: u64,
+    pub oracle_confidence: u64,
+    pub oracle_staleness_threshold: i64,
+    pub category: MarketCategory,
+    pub description: String,
+    /// Unix time by which `min_liquidity` must be reached; ignored when `min_liquidity` is 0.
+    pub funding_deadline: i64,
+    /// Betting closes this many seconds before `settle_time`.
+    pub betting_cutoff_secs: i64,
+    /// Fee charged on `sell_position` proceeds.
+    pub exit_fee_bps: u16,
+    /// Cap on `yes_pool + no_pool`; 0 means uncapped.
+    pub max_total_pot: u64,
+    /// Cap on any one user's stake in the market; 0 means uncapped.
+    pub max_user_stake: u64,
+    /// Record stakes as YES/NO outcome tokens instead of `Position` accounts.
+    pub tokenized: bool,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self {
+            fee_bps: 100,                          // 1%
+            min_bet_amount: 100_000,               // 0.1 token
+            max_bet_amount: 1_000_000_000_000,     // 1M tokens
+            min_liquidity: 0,
+            oracle_confidence: 500,                // 5%
+            oracle_staleness_threshold: 60,        // 60s
+            category: MarketCategory::Crypto,
+            description: String::new(),
+            funding_deadline: 0,
+            betting_cutoff_secs: 0,
+            exit_fee_bps: 100,                     // 1%
+            max_total_pot: 0,
+            max_user_stake: 0,
+            tokenized: false,
+        }
+    }
+}
+
+impl MarketConfig {
+    pub const MAX_DESCRIPTION_LEN: usize = 128;
+
+    pub fn validate(&self, bounds: &MarketParams) -> Result<()> {
+        require!(self.fee_bps <= bounds.max_fee_bps, PredictionMarketError::InvalidParameter);
+        require!(self.exit_fee_bps <= bounds.max_fee_bps, PredictionMarketError::InvalidParameter);
+        require!(
+            self.min_bet_amount >= bounds.min_bet_amount,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.max_bet_amount <= bounds.max_bet_amount,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.min_bet_amount <= self.max_bet_amount,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.min_liquidity <= bounds.max_min_liquidity,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.oracle_confidence > 0 && self.oracle_confidence <= bounds.max_oracle_confidence_bps,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.oracle_staleness_threshold > 0
+                && self.oracle_staleness_threshold <= bounds.oracle_staleness_threshold,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.description.len() <= Self::MAX_DESCRIPTION_LEN,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.max_user_stake == 0 || self.max_user_stake >= self.min_bet_amount,
+            PredictionMarketError::InvalidParameter
+        );
+        // Outcome tokens can be spread across wallets, so a per-user cap
+        // cannot be enforced on tokenized markets
+        require!(
+            !self.tokenized || self.max_user_stake == 0,
+            PredictionMarketError::InvalidParameter
+        );
+        require!(
+            self.max_total_pot == 0 || self.max_total_pot >= self.min_liquidity.max(self.min_bet_amount),
+            PredictionMarketError::InvalidParameter
+        );
+        Ok(())
+    }
+
+    /// Checks the time-based settings against the market's creation and settlement times.
+    pub fn validate_schedule(&self, current_time: i64, settle_time: i64) -> Result<()> {
+        require!(self.betting_cutoff_secs >= 0, PredictionMarketError::InvalidParameter);
+        let betting_close_time = self.betting_close_time(settle_time)?;
+        require!(
+            betting_close_time > current_time,
+            PredictionMarketError::InvalidParameter
+        );
+        if self.min_liquidity > 0 {
+            require!(
+                self.funding_deadline > current_time && self.funding_deadline <= betting_close_time,
+                PredictionMarketError::InvalidParameter
+            );
+        }
+        Ok(())
+    }
+
+    pub fn betting_close_time(&self, settle_time: i64) -> Result<i64> {
+        settle_time
+            .checked_sub(self.betting_cutoff_secs)
+            .ok_or_else(overflow_err)
+    }
+
+    /// Zero-padded fixed-size copy of `description` for storage on `Market`.
+    pub fn description_bytes(&self) -> Result<[u8; 128]> {
+        encode_fixed_str(&self.description)
+    }
+}
+
+// ---------- Fixed-size strings ----------
+
+/// Copies `value` into a zero-padded buffer. Embedded NULs are rejected since
+/// the first NUL marks the end of the string on decode.
+pub fn encode_fixed_str<const N: usize>(value: &str) -> Result<[u8; N]> {
+    let bytes = value.as_bytes();
+    require!(bytes.len() <= N, PredictionMarketError::InvalidMetadata);
+    require!(!bytes.contains(&0), PredictionMarketError::InvalidMetadata);
+    let mut out = [0u8; N];
+    out[..bytes.len()].copy_from_slice(bytes);
+    Ok(out)
+}
+
+/// Reads a zero-padded buffer written by `encode_fixed_str`.
+pub fn decode_fixed_str(bytes: &[u8]) -> Result<&str> {
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..len]).map_err(|_| PredictionMarketError::InvalidMetadata.into())
+}
+
 // ---------- Error helpers & math ----------
 
 #[inline(always)]
@@ -422,7 +422,23 @@ class MarketManager {
                     new anchor.BN(Math.floor(priceToUse * 100)),
                     new anchor.BN(targetChangeBps),
                     new anchor.BN(Math.floor(Date.now() / 1000) + durationSeconds),
-                    null // Use creator as resolver
+                    null, // Use creator as resolver
+                    {
+                        feeBps: 100,
+                        minBetAmount: new anchor.BN(100_000),
+                        maxBetAmount: new anchor.BN(1_000_000_000_000),
+                        minLiquidity: new anchor.BN(0),
+                        oracleConfidence: new anchor.BN(500),
+                        oracleStalenessThreshold: new anchor.BN(60),
+                        category: { crypto: {} },
+                        description: name,
+                        fundingDeadline: new anchor.BN(0),
+                        bettingCutoffSecs: new anchor.BN(0),
+                        exitFeeBps: 100,
+                        maxTotalPot: new anchor.BN(0),
+                        maxUserStake: new anchor.BN(0),
+                        tokenized: false,
+                    }
                 )
                 .accounts({
                     market: marketPda,
@@ -1,7 +1,80 @@
+#[cfg(test)]
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::clock::Clock;
+#[cfg(test)]
 use pythpredict::state::*;
-use pythpredict::errors::PredictionMarketError;
+
+#[cfg(test)]
+fn create_test_market() -> Market {
+    Market {
+        creator: Pubkey::new_unique(),
+        pyth_feed: Pubkey::new_unique(),
+        target_price: 50000,
+        settle_time: 1234567890,
+        yes_pool: 0,
+        no_pool: 0,
+        collateral_mint: Pubkey::new_unique(),
+        is_resolved: false,
+        winning_outcome: None,
+        nonce: 0,
+        bump: 254,
+        resolver_authority: Pubkey::new_unique(),
+        total_volume: 0,
+        fee_bps: 100,
+        final_price: None,
+        total_fees_collected: 0,
+        fee_collector: Pubkey::new_unique(),
+        oracle_confidence: 500,
+        min_bet_amount: 100_000,
+        max_bet_amount: 1_000_000_000_000,
+        market_status: MarketStatus::Active,
+        created_at: 0,
+        description: [0u8; 128],
+        category: MarketCategory::Crypto,
+        oracle_last_update: 0,
+        emergency_paused: false,
+        min_liquidity: 0,
+        liquidity_locked_until: 1234567890,
+        oracle_staleness_threshold: 60,
+        metadata_uri: [0u8; 200],
+        metadata_hash: [0u8; 32],
+        unclaimed_positions: 0,
+        pause_scope: 0,
+        funding_deadline: 0,
+        betting_close_time: 1234567890,
+        bond_amount: 0,
+        bond_settled: false,
+        exit_fee_bps: 100,
+        max_total_pot: 0,
+        max_user_stake: 0,
+        tokenized: false,
+        complete_sets: 0,
+        open_positions: 0,
+        settled_at: 0,
+        registry_pages: [Pubkey::default(); 3],
+        order_books: 0,
+        order_escrow: 0,
+        target_change_bps: 0,
+        referral_escrow: 0,
+    }
+}
+
+#[cfg(test)]
+fn create_test_position() -> Position {
+    Position {
+        market: Pubkey::new_unique(),
+        better: Pubkey::new_unique(),
+        yes_amount: 0,
+        no_amount: 0,
+        claimed: false,
+        entry_odds_yes: 0,
+        entry_odds_no: 0,
+        bet_timestamp: 0,
+        total_invested: 0,
+        pending_payout: 0,
+        fees_paid: 0,
+        referral_fees: 0,
+    }
+}
 
 #[cfg(test)]
 mod market_tests {
@@ -10,8 +83,181 @@ mod market_tests {
     #[test]
     fn test_market_size() {
         // Ensure the SIZE constant matches the actual struct size
-        assert!(Market::SIZE <= 10240, "Market too large for Solana");
-        assert!(Market::SIZE >= 100, "Market suspiciously small");
+        const { assert!(Market::SIZE <= 10240, "Market too large for Solana") };
+        const { assert!(Market::SIZE >= 100, "Market suspiciously small") };
+    }
+
+    #[test]
+    fn test_title_and_metadata_uri_round_trip() {
+        let mut market = create_test_market();
+        assert_eq!(market.title().unwrap(), "");
+
+        market.description = encode_fixed_str("Will BTC close above $100k? ₿").unwrap();
+        market.metadata_uri = encode_fixed_str("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi").unwrap();
+        assert_eq!(market.title().unwrap(), "Will BTC close above $100k? ₿");
+        assert_eq!(
+            market.metadata_uri().unwrap(),
+            "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+        );
+    }
+
+    #[test]
+    fn test_fixed_str_validation() {
+        assert!(encode_fixed_str::<4>("abcd").is_ok());
+        assert!(encode_fixed_str::<4>("abcde").is_err());
+        assert!(encode_fixed_str::<8>("a\0b").is_err());
+
+        // Bytes that were never written through encode_fixed_str must still decode safely
+        let mut market = create_test_market();
+        market.description[0] = 0xff;
+        assert!(market.title().is_err());
+    }
+
+    #[test]
+    fn test_can_close() {
+        let mut market = create_test_market();
+        market.unclaimed_positions = 2;
+        assert!(!market.can_close(market.settle_time));
+
+        market.is_resolved = true;
+        assert!(!market.can_close(market.settle_time));
+        assert!(market.can_close(market.sweep_deadline()));
+
+        market.unclaimed_positions = 0;
+        assert!(market.can_close(market.settle_time));
+    }
+
+    #[test]
+    fn test_open_position_accounts_block_close() {
+        let mut market = Market {
+            is_resolved: true,
+            ..create_test_market()
+        };
+        market.track_position().unwrap();
+        market.track_position().unwrap();
+        assert_eq!(market.unclaimed_positions, 2);
+
+        // A claimed position still holds its account open
+        market.unclaimed_positions -= 1;
+        market.untrack_position(false);
+        assert_eq!(market.unclaimed_positions, 0);
+        assert_eq!(market.open_positions, 1);
+        assert!(!market.can_close(market.settle_time));
+        assert!(!market.can_close(market.sweep_deadline()));
+
+        market.untrack_position(true);
+        assert_eq!(market.unclaimed_positions, 0);
+        assert_eq!(market.open_positions, 0);
+        assert!(market.can_close(market.settle_time));
+    }
+
+    #[test]
+    fn test_resolves_yes() {
+        let mut market = Market { target_price: 50_000, ..create_test_market() };
+
+        // Any move
+        assert!(market.resolves_yes(50_001));
+        assert!(market.resolves_yes(49_999));
+        assert!(!market.resolves_yes(50_000));
+
+        // Up at least 5%
+        market.target_change_bps = 500;
+        assert!(market.resolves_yes(52_500));
+        assert!(!market.resolves_yes(52_499));
+        assert!(!market.resolves_yes(40_000));
+
+        // Down at least 5%
+        market.target_change_bps = -500;
+        assert!(market.resolves_yes(47_500));
+        assert!(!market.resolves_yes(47_501));
+        assert!(!market.resolves_yes(60_000));
+    }
+
+    #[test]
+    fn test_order_escrow_blocks_close() {
+        let mut market = Market {
+            is_resolved: true,
+            ..create_test_market()
+        };
+        market.order_books |= 1 << Outcome::Yes as u8;
+        assert!(market.has_order_book(Outcome::Yes));
+        assert!(!market.has_order_book(Outcome::No));
+
+        // A resting bid and the shares it bought, settled one at a time
+        market.escrow_order_funds(5_000_000).unwrap();
+        market.escrow_order_funds(10_000_000).unwrap();
+        market.release_order_funds(10_000_000).unwrap();
+        assert!(!market.can_close(market.settle_time));
+        assert!(!market.can_close(market.sweep_deadline()));
+
+        market.release_order_funds(5_000_000).unwrap();
+        assert!(market.can_close(market.settle_time));
+        assert!(market.release_order_funds(1).is_err());
+    }
+
+    #[test]
+    fn test_bond_dispute_window() {
+        let mut market = create_test_market();
+        let now = market.settle_time;
+        assert!(!market.can_reclaim_bond(now));
+
+        market.cancel(now);
+        assert_eq!(market.settled_at, now);
+        assert!(!market.can_reclaim_bond(now));
+        assert!(!market.can_reclaim_bond(now + Market::BOND_DISPUTE_PERIOD - 1));
+        assert!(market.can_reclaim_bond(now + Market::BOND_DISPUTE_PERIOD));
+    }
+
+    #[test]
+    fn test_tokenized_market_waits_for_sweep() {
+        let market = Market {
+            is_resolved: true,
+            unclaimed_positions: 0,
+            tokenized: true,
+            ..create_test_market()
+        };
+        assert!(!market.can_close(market.settle_time));
+        assert!(market.can_close(market.sweep_deadline()));
+    }
+
+    #[test]
+    fn test_complete_sets_wait_for_sweep() {
+        let mut market = Market {
+            is_resolved: true,
+            unclaimed_positions: 0,
+            complete_sets: 1_000_000,
+            ..create_test_market()
+        };
+        assert!(!market.can_close(market.settle_time));
+        assert!(market.can_close(market.sweep_deadline()));
+
+        market.complete_sets = 0;
+        assert!(market.can_close(market.settle_time));
+    }
+
+    #[test]
+    fn test_share_value() {
+        let mut market = create_test_market();
+        assert!(market.share_value(Outcome::Yes, 100).is_err());
+
+        market.is_resolved = true;
+        market.winning_outcome = Some(1);
+        assert_eq!(market.share_value(Outcome::No, 100).unwrap(), 100);
+        assert_eq!(market.share_value(Outcome::Yes, 100).unwrap(), 0);
+
+        // Cancelled: each side is worth half, rounded down
+        let cancelled = Market { market_status: MarketStatus::Cancelled, ..create_test_market() };
+        assert_eq!(cancelled.share_value(Outcome::Yes, 100).unwrap(), 50);
+        assert_eq!(cancelled.share_value(Outcome::No, 101).unwrap(), 50);
+        assert_eq!(cancelled.share_value(Outcome::No, 1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_outcome_mint_seeds() {
+        assert_eq!(Outcome::Yes.mint_seed(), b"yes_mint");
+        assert_eq!(Outcome::No.mint_seed(), b"no_mint");
+        assert_eq!(Outcome::Yes.share_mint_seed(), b"yes_share_mint");
+        assert_eq!(Outcome::No.share_mint_seed(), b"no_share_mint");
     }
 
     #[test]
@@ -53,91 +299,772 @@ mod market_tests {
     }
 
     #[test]
-    fn test_can_resolve() {
-        let mut market = create_test_market();
-        market.is_resolved = false;
-        market.settle_time = 0; // Past time
+    fn test_is_native() {
+        let mut market = create_test_market();
+        assert!(!market.is_native());
+
+        market.collateral_mint = "So11111111111111111111111111111111111111112".parse().unwrap();
+        assert!(market.is_native());
+    }
+
+    #[test]
+    fn test_check_min_payout() {
+        let mut market = create_test_market();
+        market.yes_pool = 100_000_000;
+        market.no_pool = 100_000_000;
+
+        let quoted = market.get_expected_payout(10_000_000, Outcome::Yes).unwrap();
+        assert!(market.check_min_payout(10_000_000, Outcome::Yes, None).is_ok());
+        assert!(market.check_min_payout(10_000_000, Outcome::Yes, Some(quoted)).is_ok());
+
+        // A large YES bet lands first and worsens the odds
+        market.yes_pool += 500_000_000;
+        let err = market.check_min_payout(10_000_000, Outcome::Yes, Some(quoted)).unwrap_err();
+        assert_eq!(err, pythpredict::errors::PredictionMarketError::SlippageExceeded.into());
+    }
+
+    #[test]
+    fn test_get_exit_value() {
+        let mut market = create_test_market();
+        market.yes_pool = 300_000_000;
+        market.no_pool = 100_000_000;
+
+        // Small exits sell close to par, large ones at a growing discount
+        let small = market.get_exit_value(3_000_000, Outcome::Yes).unwrap();
+        assert!((2_990_000..3_000_000).contains(&small));
+        let large = market.get_exit_value(300_000_000, Outcome::Yes).unwrap();
+        assert_eq!(large, 150_000_000);
+        assert_eq!(market.get_exit_value(0, Outcome::No).unwrap(), 0);
+
+        // With nothing on the other side there is nothing to discount against
+        market.no_pool = 0;
+        assert_eq!(market.get_exit_value(30_000_000, Outcome::Yes).unwrap(), 30_000_000);
+        market.no_pool = 100_000_000;
+
+        // Cannot sell more than the side's pool holds
+        assert!(market.get_exit_value(100_000_001, Outcome::No).is_err());
+    }
+
+    #[test]
+    fn test_exit_value_falls_when_odds_move_against_seller() {
+        // A large YES bet moves the odds, then the market bets NO in response
+        let mut market = create_test_market();
+        market.yes_pool = 200_000_000;
+        market.no_pool = 100_000_000;
+        let stake = 150_000_000;
+        let before = market.get_exit_value(stake, Outcome::Yes).unwrap();
+
+        market.no_pool = 600_000_000;
+        let after = market.get_exit_value(stake, Outcome::Yes).unwrap();
+
+        assert_eq!(before, 112_500_000);
+        assert!(after < before);
+        assert!(after < 100_000_000);
+    }
+
+    #[test]
+    fn test_exit_value_large_pools() {
+        let mut market = create_test_market();
+        market.yes_pool = u64::MAX / 2;
+        market.no_pool = u64::MAX / 2;
+        let value = market.get_exit_value(u64::MAX / 2, Outcome::Yes).unwrap();
+        assert!((u64::MAX / 4 - 1..=u64::MAX / 4 + 1).contains(&value));
+    }
+
+    #[test]
+    fn test_can_resolve() {
+        let mut market = create_test_market();
+        market.is_resolved = false;
+        market.settle_time = 0; // Past time
+
+        // Should be able to resolve if not resolved and past settle time
+        assert!(!market.is_resolved);
+    }
+}
+
+#[cfg(test)]
+mod funding_tests {
+    use super::*;
+
+    fn create_pending_market() -> Market {
+        Market {
+            market_status: MarketStatus::PendingLiquidity,
+            min_liquidity: 1_000,
+            funding_deadline: 500,
+            ..create_test_market()
+        }
+    }
+
+    #[test]
+    fn test_activates_once_funded() {
+        let mut market = create_pending_market();
+        assert!(!market.is_active());
+        assert!(!market.activate_if_funded());
+
+        market.yes_pool = 500;
+        market.no_pool = 500;
+        assert!(market.activate_if_funded());
+        assert!(market.is_active());
+
+        // Already active: no further transition
+        assert!(!market.activate_if_funded());
+    }
+
+    #[test]
+    fn test_zero_min_liquidity_activates_immediately() {
+        let mut market = Market { min_liquidity: 0, ..create_pending_market() };
+        assert!(market.activate_if_funded());
+    }
+
+    #[test]
+    fn test_cancel_after_funding_deadline() {
+        let mut market = create_pending_market();
+        assert!(!market.cancel_if_funding_expired(499));
+        assert!(market.cancel_if_funding_expired(500));
+        assert!(market.is_cancelled());
+
+        // Funded markets are never auto-cancelled
+        let mut funded = create_test_market();
+        assert!(!funded.cancel_if_funding_expired(i64::MAX));
+    }
+
+    #[test]
+    fn test_pending_market_can_be_cancelled_and_closed() {
+        let mut market = create_pending_market();
+        assert!(market.can_cancel());
+
+        // Nothing was ever staked, so the cancelled market closes right away
+        assert!(market.cancel_if_funding_expired(500));
+        assert!(!market.can_cancel());
+        assert!(market.can_close(500));
+
+        let resolved = Market { is_resolved: true, ..create_test_market() };
+        assert!(!resolved.can_cancel());
+    }
+
+    #[test]
+    fn test_funding_deadline_schedule() {
+        let config = MarketConfig { min_liquidity: 1_000, funding_deadline: 150, ..MarketConfig::default() };
+        assert!(config.validate_schedule(100, 200).is_ok());
+        assert!(config.validate_schedule(150, 200).is_err());
+        assert!(config.validate_schedule(100, 149).is_err());
+
+        // No liquidity requirement, no deadline needed
+        assert!(MarketConfig::default().validate_schedule(100, 200).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod betting_cutoff_tests {
+    use super::*;
+
+    #[test]
+    fn test_betting_close_time_validation() {
+        let config = MarketConfig { betting_cutoff_secs: 30, ..MarketConfig::default() };
+        assert_eq!(config.betting_close_time(200).unwrap(), 170);
+        assert!(config.validate_schedule(100, 200).is_ok());
+
+        // Cutoff already in the past
+        assert!(config.validate_schedule(170, 200).is_err());
+
+        let negative = MarketConfig { betting_cutoff_secs: -1, ..MarketConfig::default() };
+        assert!(negative.validate_schedule(100, 200).is_err());
+
+        // Funding must complete before betting closes
+        let funded = MarketConfig {
+            min_liquidity: 1_000,
+            funding_deadline: 180,
+            betting_cutoff_secs: 30,
+            ..MarketConfig::default()
+        };
+        assert!(funded.validate_schedule(100, 200).is_err());
+    }
+
+    #[test]
+    fn test_status_after_cutoff() {
+        let market = Market { betting_close_time: 100, settle_time: 200, ..create_test_market() };
+        assert!(market.is_betting_open(99));
+        assert!(!market.is_betting_open(100));
+        assert_eq!(market.status_at(99), MarketStatus::Active);
+        assert_eq!(market.status_at(150), MarketStatus::Closed);
+
+        let resolved = Market { market_status: MarketStatus::Resolved, ..market };
+        assert_eq!(resolved.status_at(250), MarketStatus::Resolved);
+    }
+}
+
+#[cfg(test)]
+mod template_tests {
+    use super::*;
+
+    fn create_test_template_params() -> MarketTemplateParams {
+        MarketTemplateParams {
+            duration: 3600,
+            fee_bps: 100,
+            min_bet_amount: 1_000_000,
+            max_bet_amount: 100_000_000,
+            betting_cutoff_secs: 300,
+            category: MarketCategory::Crypto,
+            target_change_bps: 500,
+        }
+    }
+
+    #[test]
+    fn test_template_params_validation() {
+        let bounds = MarketParams::default();
+        assert!(create_test_template_params().validate(&bounds).is_ok());
+
+        let too_short = MarketTemplateParams { duration: bounds.min_settlement_time, ..create_test_template_params() };
+        assert!(too_short.validate(&bounds).is_err());
+
+        let cutoff_too_long = MarketTemplateParams { betting_cutoff_secs: 3600, ..create_test_template_params() };
+        assert!(cutoff_too_long.validate(&bounds).is_err());
+
+        let fee_too_high = MarketTemplateParams { fee_bps: bounds.max_fee_bps + 1, ..create_test_template_params() };
+        assert!(fee_too_high.validate(&bounds).is_err());
+
+        let falls_below_zero = MarketTemplateParams { target_change_bps: -10_000, ..create_test_template_params() };
+        assert!(falls_below_zero.validate(&bounds).is_err());
+
+        let falls_by_half = MarketTemplateParams { target_change_bps: -5_000, ..create_test_template_params() };
+        assert!(falls_by_half.validate(&bounds).is_ok());
+    }
+
+    #[test]
+    fn test_template_market_config() {
+        let params = create_test_template_params();
+        let template = MarketTemplate {
+            template_id: 1,
+            admin: Pubkey::new_unique(),
+            pyth_feed: Pubkey::new_unique(),
+            collateral_mint: Pubkey::new_unique(),
+            resolver_authority: Pubkey::new_unique(),
+            duration: params.duration,
+            fee_bps: params.fee_bps,
+            min_bet_amount: params.min_bet_amount,
+            max_bet_amount: params.max_bet_amount,
+            betting_cutoff_secs: params.betting_cutoff_secs,
+            category: params.category,
+            target_change_bps: params.target_change_bps,
+            is_active: true,
+            markets_created: 0,
+            bump: 255,
+        };
+
+        let config = template.market_config();
+        assert_eq!(config.fee_bps, 100);
+        assert_eq!(config.min_bet_amount, 1_000_000);
+        assert_eq!(config.betting_close_time(10_000).unwrap(), 9_700);
+        assert!(config.validate(&MarketParams::default()).is_ok());
+    }
+
+    #[test]
+    fn test_opening_price_checks() {
+        use pythpredict::instructions::confidence_bps;
+
+        assert_eq!(confidence_bps(50_000, 250), 50);
+        assert_eq!(confidence_bps(0, 1), u64::MAX);
+        assert_eq!(confidence_bps(-1, 1), u64::MAX);
+
+        let market = create_test_market();
+        let now = 10_000;
+        assert!(market.validate_oracle_price(confidence_bps(50_000, 250), now, now).is_ok());
+        // Confidence wider than the market allows
+        assert!(market.validate_oracle_price(confidence_bps(50_000, 5_000), now, now).is_err());
+        // Published longer ago than the staleness threshold
+        let stale = now - market.oracle_staleness_threshold - 1;
+        assert!(market.validate_oracle_price(confidence_bps(50_000, 250), stale, now).is_err());
+    }
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+
+    fn create_test_page() -> MarketRegistryPage {
+        MarketRegistryPage {
+            registry: Pubkey::new_unique(),
+            page_index: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_registry_current_page() {
+        let mut registry = MarketRegistry {
+            kind: RegistryKind::Category,
+            market_count: 0,
+            bump: 255,
+        };
+        assert_eq!(registry.current_page(), 0);
+
+        registry.market_count = MarketRegistryPage::PAGE_SIZE as u64 - 1;
+        assert_eq!(registry.current_page(), 0);
+
+        registry.market_count = MarketRegistryPage::PAGE_SIZE as u64;
+        assert_eq!(registry.current_page(), 1);
+    }
+
+    #[test]
+    fn test_creator_profile_next_nonce() {
+        let mut profile = CreatorProfile {
+            creator: Pubkey::new_unique(),
+            market_count: 0,
+            bump: 255,
+        };
+        assert_eq!(profile.next_nonce().unwrap(), COUNTER_NONCE_FLAG);
+        assert_eq!(profile.next_nonce().unwrap(), COUNTER_NONCE_FLAG | 1);
+        assert_eq!(profile.market_count, 2);
+
+        // Explicit nonces pass through and never land in the counter space
+        assert_eq!(profile.resolve_nonce(Some(1)), 1);
+        assert_eq!(profile.resolve_nonce(None), COUNTER_NONCE_FLAG | 2);
+        assert_ne!(profile.resolve_nonce(Some(2)), profile.resolve_nonce(None));
+
+        profile.market_count = u64::MAX;
+        assert!(profile.next_nonce().is_err());
+        assert_eq!(profile.market_count, u64::MAX);
+    }
+
+    #[test]
+    fn test_registry_page_push_until_full() {
+        let mut page = create_test_page();
+        for _ in 0..MarketRegistryPage::PAGE_SIZE {
+            let entry = RegistryEntry { market: Pubkey::new_unique(), status: MarketStatus::Active };
+            assert!(page.push(entry).is_ok());
+        }
+
+        let overflow = RegistryEntry { market: Pubkey::new_unique(), status: MarketStatus::Active };
+        assert!(page.push(overflow).is_err());
+
+        // A full page must still fit in the allocated account space
+        let mut data = Vec::new();
+        page.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= MarketRegistryPage::SIZE);
+    }
+
+    #[test]
+    fn test_registry_page_status_and_removal() {
+        let mut page = create_test_page();
+        let market = Pubkey::new_unique();
+        page.push(RegistryEntry { market, status: MarketStatus::PendingLiquidity }).unwrap();
+
+        assert!(page.set_status(&market, MarketStatus::Resolved));
+        assert_eq!(page.entries[0].status, MarketStatus::Resolved);
+        assert!(!page.set_status(&Pubkey::new_unique(), MarketStatus::Cancelled));
+
+        assert!(page.remove(&market));
+        assert!(page.entries.is_empty());
+        assert!(!page.remove(&market));
+    }
+}
+
+#[cfg(test)]
+mod pause_tests {
+    use super::*;
+
+    fn create_test_config() -> ProtocolConfig {
+        ProtocolConfig {
+            admin: Pubkey::new_unique(),
+            pauser: Pubkey::new_unique(),
+            global_paused: false,
+            global_pause_scope: 0,
+            bump: 255,
+            max_open_positions: 0,
+            referral_share_bps: 0,
+        }
+    }
+
+    #[test]
+    fn test_pause_scope_validation() {
+        assert!(PauseScope::is_valid(PauseScope::BETS));
+        assert!(PauseScope::is_valid(PauseScope::ALL));
+        assert!(!PauseScope::is_valid(0));
+        assert!(!PauseScope::is_valid(1 << 3));
+    }
+
+    #[test]
+    fn test_market_pause_respects_scope() {
+        let mut market = create_test_market();
+        assert!(market.is_active());
+
+        market.emergency_paused = true;
+        market.pause_scope = PauseScope::CLAIMS;
+        assert!(market.is_active());
+        assert!(market.is_paused_for(PauseScope::CLAIMS));
+        assert!(!market.is_paused_for(PauseScope::LIQUIDITY));
+
+        market.pause_scope = PauseScope::BETS | PauseScope::LIQUIDITY;
+        assert!(!market.is_active());
+
+        let config = create_test_config();
+        assert!(config.require_not_paused(&market, PauseScope::CLAIMS).is_ok());
+        assert!(config.require_not_paused(&market, PauseScope::BETS).is_err());
+    }
+
+    #[test]
+    fn test_global_pause() {
+        let market = create_test_market();
+        let mut config = create_test_config();
+
+        config.global_paused = true;
+        config.global_pause_scope = PauseScope::ALL;
+        assert!(config.require_not_paused(&market, PauseScope::LIQUIDITY).is_err());
+
+        config.global_pause_scope = PauseScope::BETS;
+        assert!(config.require_not_paused(&market, PauseScope::LIQUIDITY).is_ok());
+    }
+
+    #[test]
+    fn test_pause_authority() {
+        let config = create_test_config();
+        assert!(config.is_pause_authority(&config.admin));
+        assert!(config.is_pause_authority(&config.pauser));
+        assert!(!config.is_pause_authority(&Pubkey::new_unique()));
+    }
+}
+
+#[cfg(test)]
+mod position_tests {
+    use super::*;
+
+    #[test]
+    fn test_position_size() {
+        assert!(Position::SIZE >= std::mem::size_of::<Position>());
+    }
+
+    #[test]
+    fn test_get_total_stake() {
+        let position = Position {
+            yes_amount: 100,
+            no_amount: 50,
+            ..create_test_position()
+        };
+
+        assert_eq!(position.get_total_stake(), 150);
+    }
+
+    #[test]
+    fn test_holds_slot() {
+        let mut position = create_test_position();
+        assert!(!position.holds_slot());
+
+        position.no_amount = 100;
+        assert!(position.holds_slot());
+
+        position.claimed = true;
+        assert!(!position.holds_slot());
+    }
+
+    #[test]
+    fn test_can_release_referral_fees() {
+        let mut market = create_test_market();
+        let mut position = Position {
+            yes_amount: 100,
+            referral_fees: 10,
+            ..create_test_position()
+        };
+        assert!(!position.can_release_referral_fees(&market));
+
+        market.is_resolved = true;
+        assert!(position.can_release_referral_fees(&market));
+
+        // An exited position can no longer be refunded
+        market.is_resolved = false;
+        position.yes_amount = 0;
+        assert!(position.can_release_referral_fees(&market));
+
+        position.referral_fees = 0;
+        assert!(!position.can_release_referral_fees(&market));
+    }
+
+    #[test]
+    fn test_has_position() {
+        let mut position = create_test_position();
+
+        assert!(!position.has_position());
+
+        position.yes_amount = 100;
+        assert!(position.has_position());
+
+        position.yes_amount = 0;
+        position.no_amount = 50;
+        assert!(position.has_position());
+    }
+
+    #[test]
+    fn test_get_winning_stake() {
+        let position = Position {
+            yes_amount: 100,
+            no_amount: 50,
+            ..create_test_position()
+        };
+
+        assert_eq!(position.get_winning_stake(0), 100); // Yes wins
+        assert_eq!(position.get_winning_stake(1), 50);  // No wins
+        assert_eq!(position.get_winning_stake(2), 0);   // Invalid
+    }
+
+    #[test]
+    fn test_get_fees_paid() {
+        let position = Position {
+            yes_amount: 990,
+            no_amount: 495,
+            total_invested: 1500,
+            fees_paid: 15,
+            ..create_test_position()
+        };
+        assert_eq!(position.get_fees_paid(), 15);
+
+        // Liquidity positions carry no fee
+        let lp = Position {
+            yes_amount: 1000,
+            no_amount: 1000,
+            total_invested: 2000,
+            ..create_test_position()
+        };
+        assert_eq!(lp.get_fees_paid(), 0);
+    }
+
+    #[test]
+    fn test_cancelled_position_needs_refund() {
+        let mut market = create_test_market();
+        market.market_status = MarketStatus::Cancelled;
+        let position = Position {
+            yes_amount: 100,
+            ..create_test_position()
+        };
+        assert!(!position.is_settled(&market));
+
+        market.track_position().unwrap();
+        assert!(!market.can_close(market.settle_time));
+        assert!(!market.can_close(market.sweep_deadline()));
+
+        // Swept after the deadline, the market can close
+        market.untrack_position(position.claimed);
+        assert_eq!(market.unclaimed_positions, 0);
+        assert!(market.can_close(market.sweep_deadline()));
+    }
+
+    #[test]
+    fn test_is_settled() {
+        let mut market = create_test_market();
+        let mut position = Position {
+            yes_amount: 100,
+            ..create_test_position()
+        };
+
+        // Unresolved market with stake
+        assert!(!position.is_settled(&market));
+
+        // Winner who has not claimed yet
+        market.is_resolved = true;
+        market.winning_outcome = Some(0);
+        assert!(!position.is_settled(&market));
+
+        // Confirmed loss
+        market.winning_outcome = Some(1);
+        assert!(position.is_settled(&market));
+
+        // Claimed winner
+        market.winning_outcome = Some(0);
+        position.claimed = true;
+        assert!(position.is_settled(&market));
+
+        // Empty position is always closable
+        market.is_resolved = false;
+        market.winning_outcome = None;
+        let empty = create_test_position();
+        assert!(empty.is_settled(&market));
+    }
+
+    #[test]
+    fn test_reduce_stake() {
+        let mut position = Position {
+            yes_amount: 99_000_000,
+            no_amount: 0,
+            total_invested: 100_000_000,
+            fees_paid: 1_000_000,
+            ..create_test_position()
+        };
+
+        let share = position.reduce_stake(Outcome::Yes, 49_500_000).unwrap();
+        assert_eq!(share, StakeShare { invested: 50_000_000, fees_paid: 500_000 });
+        assert_eq!(position.yes_amount, 49_500_000);
+        assert_eq!(position.total_invested, 50_000_000);
+        assert_eq!(position.get_fees_paid(), 500_000);
+
+        assert!(position.reduce_stake(Outcome::No, 1).is_err());
+        assert!(position.reduce_stake(Outcome::Yes, 49_500_001).is_err());
+        assert!(position.reduce_stake(Outcome::Yes, 0).is_err());
+    }
+
+    #[test]
+    fn test_transfer_needs_open_market() {
+        let now = 1_000;
+        let mut market = create_test_market();
+        assert!(market.can_transfer_positions(now));
+
+        // Closed to betting but not yet resolved is still tradeable
+        market.market_status = MarketStatus::Closed;
+        assert!(market.can_transfer_positions(now));
+
+        market.market_status = MarketStatus::Active;
+        market.is_resolved = true;
+        assert!(!market.can_transfer_positions(now));
+
+        market.is_resolved = false;
+        market.cancel(now);
+        assert!(!market.can_transfer_positions(now));
+
+        // A pending market past its funding deadline will only ever refund
+        market.market_status = MarketStatus::PendingLiquidity;
+        market.funding_deadline = now;
+        assert!(!market.can_transfer_positions(now));
+        assert!(market.can_transfer_positions(now - 1));
+    }
+
+    #[test]
+    fn test_transfer_stake() {
+        let mut from = Position {
+            yes_amount: 99_000_000,
+            no_amount: 0,
+            total_invested: 100_000_000,
+            fees_paid: 1_000_000,
+            ..create_test_position()
+        };
+        let mut to = create_test_position();
 
-        // Should be able to resolve if not resolved and past settle time
-        assert!(!market.is_resolved);
+        let share = from.reduce_stake(Outcome::Yes, 33_000_000).unwrap();
+        to.add_stake(Outcome::Yes, 33_000_000, share).unwrap();
+
+        assert_eq!(share.invested, 33_333_333);
+        assert_eq!(to.yes_amount, 33_000_000);
+        assert_eq!(from.total_invested + to.total_invested, 100_000_000);
+        assert_eq!(from.get_fees_paid() + to.get_fees_paid(), 1_000_000);
     }
 
-    fn create_test_market() -> Market {
-        Market {
-            creator: Pubkey::new_unique(),
-            pyth_feed: Pubkey::new_unique(),
-            target_price: 50000,
-            settle_time: 1234567890,
-            yes_pool: 0,
-            no_pool: 0,
-            collateral_mint: Pubkey::new_unique(),
-            is_resolved: false,
-            winning_outcome: None,
-            nonce: 0,
-            bump: 254,
-            resolver_authority: Pubkey::new_unique(),
-            total_volume: 0,
-            fee_bps: 100,
-            final_price: None,
+    #[test]
+    fn test_referred_bet_refunds_only_protocol_fee() {
+        let config = ProtocolConfig {
+            admin: Pubkey::new_unique(),
+            pauser: Pubkey::new_unique(),
+            global_paused: false,
+            global_pause_scope: 0,
+            bump: 255,
+            max_open_positions: 0,
+            referral_share_bps: 2_500,
+        };
+
+        // Two referred bets of 100_000 at 1%: 250 of each fee goes to the referrer
+        let mut fee_vault = 0u64;
+        let mut positions = [create_test_position(), create_test_position()];
+        for position in positions.iter_mut() {
+            let (fee, stake) = calculate_fee(100_000, 100).unwrap();
+            let (_referral_fee, protocol_fee) = config.split_referral_fee(fee).unwrap();
+            fee_vault += protocol_fee;
+            position
+                .add_stake(Outcome::Yes, stake, StakeShare { invested: 100_000, fees_paid: protocol_fee })
+                .unwrap();
+        }
+
+        // Each refund takes only what that bet put in the fee vault
+        for position in &positions {
+            assert_eq!(position.get_fees_paid(), 750);
+            fee_vault -= position.get_fees_paid().min(fee_vault);
         }
+        assert_eq!(fee_vault, 0);
     }
 }
 
 #[cfg(test)]
-mod position_tests {
+mod collateral_mint_tests {
     use super::*;
+    use anchor_spl::token_2022::spl_token_2022::{
+        extension::{
+            transfer_fee::{TransferFee, TransferFeeConfig},
+            transfer_hook::TransferHook,
+            BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+        },
+        state::Mint as MintState,
+    };
+    use pythpredict::instructions::{check_mint_extensions, SUPPORTED_MINT_EXTENSIONS};
 
-    #[test]
-    fn test_position_size() {
-        assert!(Position::SIZE >= std::mem::size_of::<Position>());
+    fn mint_data(extensions: &[ExtensionType]) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<MintState>(extensions).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        for extension in extensions {
+            match extension {
+                ExtensionType::TransferFeeConfig => {
+                    state.init_extension::<TransferFeeConfig>(true).unwrap();
+                }
+                ExtensionType::TransferHook => {
+                    state.init_extension::<TransferHook>(true).unwrap();
+                }
+                other => panic!("no fixture for {:?}", other),
+            }
+        }
+        state.base = MintState { decimals: 6, is_initialized: true, ..MintState::default() };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
     }
 
     #[test]
-    fn test_get_total_stake() {
-        let position = Position {
-            market: Pubkey::new_unique(),
-            better: Pubkey::new_unique(),
-            yes_amount: 100,
-            no_amount: 50,
-            claimed: false,
-        };
-
-        assert_eq!(position.get_total_stake(), 150);
+    fn test_transfer_fee_mint_is_supported() {
+        assert!(check_mint_extensions(&mint_data(&[ExtensionType::TransferFeeConfig])).is_ok());
     }
 
     #[test]
-    fn test_has_position() {
-        let mut position = Position {
-            market: Pubkey::new_unique(),
-            better: Pubkey::new_unique(),
-            yes_amount: 0,
-            no_amount: 0,
-            claimed: false,
-        };
-
-        assert!(!position.has_position());
-
-        position.yes_amount = 100;
-        assert!(position.has_position());
+    fn test_unsupported_extensions_are_rejected() {
+        assert!(check_mint_extensions(&mint_data(&[ExtensionType::TransferHook])).is_err());
+        assert!(check_mint_extensions(&mint_data(&[
+            ExtensionType::TransferFeeConfig,
+            ExtensionType::TransferHook,
+        ]))
+        .is_err());
 
-        position.yes_amount = 0;
-        position.no_amount = 50;
-        assert!(position.has_position());
+        for extension in [
+            ExtensionType::TransferHook,
+            ExtensionType::PermanentDelegate,
+            ExtensionType::NonTransferable,
+            ExtensionType::DefaultAccountState,
+            ExtensionType::ConfidentialTransferMint,
+        ] {
+            assert!(!SUPPORTED_MINT_EXTENSIONS.contains(&extension));
+        }
     }
 
     #[test]
-    fn test_get_winning_stake() {
-        let position = Position {
-            market: Pubkey::new_unique(),
-            better: Pubkey::new_unique(),
-            yes_amount: 100,
-            no_amount: 50,
-            claimed: false,
+    fn test_landed_bet_under_transfer_fee() {
+        // 0.5% transfer fee, rounded up per transfer
+        let transfer_fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: 50.into(),
         };
+        let land = |amount: u64| amount - transfer_fee.calculate_fee(amount).unwrap();
 
-        assert_eq!(position.get_winning_stake(0), 100); // Yes wins
-        assert_eq!(position.get_winning_stake(1), 50);  // No wins
-        assert_eq!(position.get_winning_stake(2), 0);   // Invalid
+        // 1% bet fee with a quarter going to the referrer
+        let (fee, stake) = calculate_fee(1_000_000, 100).unwrap();
+        assert_eq!((fee, stake), (10_000, 990_000));
+        let landed = LandedBet {
+            stake: land(stake),
+            protocol_fee: land(7_500),
+            referral_fee: land(2_500),
+        };
+        assert_eq!(landed, LandedBet { stake: 985_050, protocol_fee: 7_462, referral_fee: 2_487 });
+        assert_eq!(landed.total().unwrap(), 994_999);
+
+        // The position's refundable fee is what reached the fee vault
+        let mut position = create_test_position();
+        position.add_stake(Outcome::Yes, landed.stake, landed.stake_share().unwrap()).unwrap();
+        assert_eq!(position.total_invested, 994_999);
+        assert_eq!(position.get_fees_paid(), 7_462);
+
+        let overflowing = LandedBet { stake: u64::MAX, protocol_fee: 1, referral_fee: 0 };
+        assert!(overflowing.total().is_err());
     }
 }
 
@@ -196,7 +1123,7 @@ mod calculation_tests {
     #[test]
     fn test_calculate_fee_overflow() {
         // Test overflow handling
-        let result = calculate_fee(u64::MAX, u64::MAX);
+        let result = calculate_fee(u64::MAX, u16::MAX);
         assert!(result.is_err());
     }
 }
@@ -210,9 +1137,484 @@ mod market_params_tests {
         let params = MarketParams::default();
         assert_eq!(params.min_bet_amount, 100_000);
         assert_eq!(params.max_bet_amount, 1_000_000_000_000);
-        assert_eq!(params.min_settlement_time, 3600);
+        assert_eq!(params.min_settlement_time, 10);
         assert_eq!(params.max_settlement_time, 365 * 24 * 3600);
         assert_eq!(params.max_price_confidence, 5);
+        assert_eq!(params.max_fee_bps, 1_000);
+    }
+
+    #[test]
+    fn test_default_market_config_is_valid() {
+        let bounds = MarketParams::default();
+        assert!(MarketConfig::default().validate(&bounds).is_ok());
+    }
+
+    #[test]
+    fn test_market_config_rejects_out_of_bounds() {
+        let bounds = MarketParams::default();
+
+        let config = MarketConfig { fee_bps: bounds.max_fee_bps + 1, ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+
+        let config = MarketConfig { min_bet_amount: bounds.min_bet_amount - 1, ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+
+        let config = MarketConfig { max_bet_amount: bounds.max_bet_amount + 1, ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+
+        // min above max
+        let config = MarketConfig {
+            min_bet_amount: 2_000_000,
+            max_bet_amount: 1_000_000,
+            ..MarketConfig::default()
+        };
+        assert!(config.validate(&bounds).is_err());
+
+        let config = MarketConfig { oracle_confidence: 0, ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+
+        let config = MarketConfig {
+            oracle_confidence: bounds.max_oracle_confidence_bps + 1,
+            ..MarketConfig::default()
+        };
+        assert!(config.validate(&bounds).is_err());
+
+        let config = MarketConfig { oracle_staleness_threshold: 3600, ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+
+        let config = MarketConfig { description: "x".repeat(129), ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+    }
+
+    #[test]
+    fn test_market_config_description_bytes() {
+        let config = MarketConfig { description: "BTC up in 60s?".to_string(), ..MarketConfig::default() };
+        let bytes = config.description_bytes().unwrap();
+        assert_eq!(&bytes[..14], b"BTC up in 60s?");
+        assert!(bytes[14..].iter().all(|b| *b == 0));
+    }
+}
+
+#[cfg(test)]
+mod limit_tests {
+    use super::*;
+
+    #[test]
+    fn test_market_capacity() {
+        let mut market = Market { yes_pool: 600, no_pool: 300, ..create_test_market() };
+        assert!(market.check_capacity(u64::MAX / 2).is_ok());
+
+        market.max_total_pot = 1_000;
+        assert!(market.check_capacity(100).is_ok());
+        assert!(market.check_capacity(101).is_err());
+    }
+
+    #[test]
+    fn test_user_stake_limit() {
+        let mut market = create_test_market();
+        assert!(market.check_user_stake(1_000_000, 1_000_000).is_ok());
+
+        market.max_user_stake = 1_500_000;
+        assert!(market.check_user_stake(1_000_000, 500_000).is_ok());
+        assert!(market.check_user_stake(1_000_000, 500_001).is_err());
+    }
+
+    #[test]
+    fn test_open_position_limit() {
+        let mut stats = UserStats {
+            user: Pubkey::new_unique(),
+            open_positions: 0,
+            bump: 255,
+            bet_count: 0,
+            referrer: None,
+        };
+        assert!(stats.open_position(2).is_ok());
+        assert!(stats.open_position(2).is_ok());
+        assert!(stats.open_position(2).is_err());
+        assert_eq!(stats.open_positions, 2);
+
+        // 0 disables the limit
+        assert!(stats.open_position(0).is_ok());
+
+        stats.close_position();
+        assert_eq!(stats.open_positions, 2);
+        stats.open_positions = 0;
+        stats.close_position();
+        assert_eq!(stats.open_positions, 0);
+    }
+
+    #[test]
+    fn test_limit_config_validation() {
+        let bounds = MarketParams::default();
+        let below_min_bet = MarketConfig { max_user_stake: 1, ..MarketConfig::default() };
+        assert!(below_min_bet.validate(&bounds).is_err());
+
+        let capped = MarketConfig {
+            max_total_pot: 1_000_000_000,
+            max_user_stake: 10_000_000,
+            ..MarketConfig::default()
+        };
+        assert!(capped.validate(&bounds).is_ok());
+
+        // Per-user caps are unenforceable once stakes are transferable tokens
+        let tokenized = MarketConfig { tokenized: true, ..capped.clone() };
+        assert!(tokenized.validate(&bounds).is_err());
+        let tokenized = MarketConfig { max_user_stake: 0, ..tokenized };
+        assert!(tokenized.validate(&bounds).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod referral_tests {
+    use super::*;
+
+    fn create_test_stats() -> UserStats {
+        UserStats {
+            user: Pubkey::new_unique(),
+            open_positions: 0,
+            bump: 255,
+            bet_count: 0,
+            referrer: None,
+        }
+    }
+
+    #[test]
+    fn test_referral_attribution_sticks() {
+        let referrer = Pubkey::new_unique();
+        let mut stats = create_test_stats();
+        assert!(stats.attribute_referrer(Some(referrer)).is_ok());
+        assert_eq!(stats.referrer, Some(referrer));
+
+        assert!(stats.attribute_referrer(Some(referrer)).is_ok());
+        assert_eq!(stats.attribute_referrer(None).unwrap(), Some(referrer));
+        assert!(stats.attribute_referrer(Some(Pubkey::new_unique())).is_err());
+        assert_eq!(stats.bet_count, 3);
+    }
+
+    #[test]
+    fn test_no_referrer_after_first_bet() {
+        let mut stats = create_test_stats();
+        assert!(stats.attribute_referrer(None).is_ok());
+        assert!(stats.attribute_referrer(Some(Pubkey::new_unique())).is_err());
+        assert!(stats.attribute_referrer(None).is_ok());
+        assert_eq!(stats.referrer, None);
+    }
+
+    #[test]
+    fn test_self_referral_rejected() {
+        let mut stats = create_test_stats();
+        assert!(stats.attribute_referrer(Some(stats.user)).is_err());
+        assert_eq!(stats.bet_count, 0);
+    }
+
+    #[test]
+    fn test_split_referral_fee() {
+        let mut config = ProtocolConfig {
+            admin: Pubkey::new_unique(),
+            pauser: Pubkey::new_unique(),
+            global_paused: false,
+            global_pause_scope: 0,
+            bump: 255,
+            max_open_positions: 0,
+            referral_share_bps: 0,
+        };
+        assert_eq!(config.split_referral_fee(1_000).unwrap(), (0, 1_000));
+
+        config.referral_share_bps = 2_500;
+        assert_eq!(config.split_referral_fee(1_000).unwrap(), (250, 750));
+        assert_eq!(config.split_referral_fee(3).unwrap(), (0, 3));
+    }
+}
+
+#[cfg(test)]
+mod order_book_tests {
+    use super::*;
+
+    fn create_test_book() -> OrderBook {
+        OrderBook {
+            market: Pubkey::new_unique(),
+            outcome: Outcome::Yes as u8,
+            next_order_id: 0,
+            bump: 255,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_price_time_priority() {
+        let mut book = create_test_book();
+        let maker = Pubkey::new_unique();
+        book.insert(OrderSide::Ask, maker, 6_000, 100, 100).unwrap();
+        book.insert(OrderSide::Ask, maker, 5_500, 100, 100).unwrap();
+        book.insert(OrderSide::Ask, maker, 5_500, 100, 100).unwrap();
+        book.insert(OrderSide::Bid, maker, 4_000, 100, 40).unwrap();
+        book.insert(OrderSide::Bid, maker, 4_500, 100, 45).unwrap();
+
+        let ask_ids: Vec<u64> = book.asks.iter().map(|order| order.id).collect();
+        assert_eq!(ask_ids, vec![1, 2, 0]);
+        let bid_prices: Vec<u16> = book.bids.iter().map(|order| order.price_bps).collect();
+        assert_eq!(bid_prices, vec![4_500, 4_000]);
+    }
+
+    #[test]
+    fn test_crossing_bid_fills_at_maker_prices() {
+        let mut book = create_test_book();
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        book.insert(OrderSide::Ask, first, 5_000, 100, 100).unwrap();
+        book.insert(OrderSide::Ask, second, 6_000, 100, 100).unwrap();
+        book.insert(OrderSide::Ask, first, 7_000, 100, 100).unwrap();
+
+        let fills = book.match_order(OrderSide::Bid, 6_000, 250).unwrap();
+        assert_eq!(fills.filled, 200);
+        assert_eq!(fills.cost, 50 + 60);
+        assert_eq!(
+            fills.credits,
+            vec![
+                Credit { owner: first, shares: 0, collateral: 50 },
+                Credit { owner: second, shares: 0, collateral: 60 },
+            ]
+        );
+
+        // A buyer pays each fill rounded up: 3 shares at 0.3333 cost 1.0 unit
+        let mut odd = create_test_book();
+        odd.insert(OrderSide::Ask, first, 3_333, 3, 3).unwrap();
+        let fills = odd.match_order(OrderSide::Bid, 3_333, 3).unwrap();
+        assert_eq!(fills.cost, 1);
+        assert_eq!(OrderBook::cost(3, 3_333).unwrap(), 0);
+
+        // Filled orders leave the book
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.asks[0].price_bps, 7_000);
+        assert_eq!(book.asks[0].quantity, 100);
+    }
+
+    #[test]
+    fn test_crossing_ask_spends_bid_escrow() {
+        let mut book = create_test_book();
+        let maker = Pubkey::new_unique();
+        let escrow = OrderBook::cost(1_000, 4_000).unwrap();
+        book.insert(OrderSide::Bid, maker, 4_000, 1_000, escrow).unwrap();
+
+        let fills = book.match_order(OrderSide::Ask, 3_000, 400).unwrap();
+        assert_eq!(fills.filled, 400);
+        assert_eq!(fills.cost, 160);
+        assert_eq!(fills.credits, vec![Credit { owner: maker, shares: 400, collateral: 0 }]);
+        assert_eq!(book.bids[0].quantity, 600);
+        assert_eq!(book.bids[0].escrow, 240);
+
+        // Does not cross
+        let fills = book.match_order(OrderSide::Ask, 4_001, 100).unwrap();
+        assert_eq!(fills, MatchResult::default());
+    }
+
+    #[test]
+    fn test_filled_bid_returns_escrow_dust() {
+        let mut book = create_test_book();
+        let maker = Pubkey::new_unique();
+        let escrow = OrderBook::cost(30_000, 3_333).unwrap();
+        assert_eq!(escrow, 9_999);
+        book.insert(OrderSide::Bid, maker, 3_333, 30_000, escrow).unwrap();
+
+        // Each fill rounds down on its own, leaving a unit of escrow unspent
+        assert_eq!(book.match_order(OrderSide::Ask, 3_333, 10_001).unwrap().cost, 3_333);
+        assert_eq!(book.match_order(OrderSide::Ask, 3_333, 10_001).unwrap().cost, 3_333);
+        let last = book.match_order(OrderSide::Ask, 3_333, 10_000).unwrap();
+        assert_eq!((last.filled, last.cost), (9_998, 3_332));
+        assert_eq!(last.credits, vec![Credit { owner: maker, shares: 9_998, collateral: 1 }]);
+        assert!(book.bids.is_empty());
+    }
+
+    #[test]
+    fn test_remove_order() {
+        let mut book = create_test_book();
+        let owner = Pubkey::new_unique();
+        let (id, _) = book.insert(OrderSide::Bid, owner, 5_000, 100, 50).unwrap();
+
+        assert!(book.remove(id, &Pubkey::new_unique()).is_err());
+        assert!(book.remove(id + 1, &owner).is_err());
+        let (side, order) = book.remove(id, &owner).unwrap();
+        assert_eq!(side, OrderSide::Bid);
+        assert_eq!(order.escrow, 50);
+        assert!(book.bids.is_empty());
+    }
+
+    #[test]
+    fn test_full_book_evicts_worst_order() {
+        let mut book = create_test_book();
+        for i in 0..OrderBook::MAX_ORDERS as u16 {
+            book.insert(OrderSide::Bid, Pubkey::new_unique(), 1 + i, 100, 1).unwrap();
+            book.insert(OrderSide::Ask, Pubkey::new_unique(), 6_000, 100, 100).unwrap();
+        }
+
+        // Same or worse price than the worst resting bid cannot get in
+        assert!(book.insert(OrderSide::Bid, Pubkey::new_unique(), 1, 100, 1).is_err());
+        assert!(book.insert(OrderSide::Ask, Pubkey::new_unique(), 6_000, 100, 100).is_err());
+
+        let worst = *book.bids.last().unwrap();
+        let (_, evicted) = book.insert(OrderSide::Bid, Pubkey::new_unique(), 5_000, 100, 50).unwrap();
+        assert_eq!(evicted, Some(worst));
+        assert_eq!(
+            worst.escrow_credit(OrderSide::Bid),
+            Credit { owner: worst.owner, shares: 0, collateral: 1 }
+        );
+        assert_eq!(book.bids.len(), OrderBook::MAX_ORDERS);
+        assert_eq!(book.bids[0].price_bps, 5_000);
+
+        let mut data = Vec::new();
+        book.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), OrderBook::SIZE);
+    }
+
+    #[test]
+    fn test_trader_balance_accumulates_credits() {
+        let owner = Pubkey::new_unique();
+        let mut balance = TraderBalance {
+            order_book: Pubkey::new_unique(),
+            owner,
+            shares: 0,
+            collateral: 0,
+            bump: 255,
+        };
+        balance.add(&Credit { owner, shares: 10, collateral: 0 }).unwrap();
+        balance.add(&Credit { owner, shares: 5, collateral: 7 }).unwrap();
+        assert_eq!((balance.shares, balance.collateral), (15, 7));
+        assert!(balance.add(&Credit { owner, shares: u64::MAX, collateral: 0 }).is_err());
+
+        let mut data = Vec::new();
+        balance.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), TraderBalance::SIZE);
+    }
+}
+
+#[cfg(test)]
+mod delegate_tests {
+    use super::*;
+
+    fn create_test_delegate() -> BettingDelegate {
+        BettingDelegate {
+            user: Pubkey::new_unique(),
+            delegate: Pubkey::new_unique(),
+            total_cap: 1_000,
+            per_bet_cap: 400,
+            spent: 0,
+            expires_at: 1_000,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_delegate_caps() {
+        let mut delegate = create_test_delegate();
+        assert!(delegate.record_spend(401, 0).is_err());
+        assert!(delegate.record_spend(400, 0).is_ok());
+        assert!(delegate.record_spend(400, 0).is_ok());
+        assert_eq!(delegate.remaining(), 200);
+
+        // Within the per-bet cap but over what is left in total
+        assert!(delegate.record_spend(201, 0).is_err());
+        assert!(delegate.record_spend(200, 0).is_ok());
+        assert_eq!(delegate.spent, 1_000);
+    }
+
+    #[test]
+    fn test_delegate_expiry() {
+        let mut delegate = create_test_delegate();
+        assert!(!delegate.is_expired(999));
+        assert!(delegate.is_expired(1_000));
+        assert!(delegate.record_spend(100, 1_000).is_err());
+        assert_eq!(delegate.spent, 0);
+    }
+
+    #[test]
+    fn test_delegate_size() {
+        let delegate = create_test_delegate();
+        let mut data = Vec::new();
+        delegate.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), BettingDelegate::SIZE);
+    }
+}
+
+#[cfg(test)]
+mod collateral_tests {
+    use super::*;
+
+    fn create_test_collateral(decimals: u8) -> CollateralConfig {
+        let bounds = MarketParams::for_decimals(decimals).unwrap();
+        CollateralConfig {
+            mint: Pubkey::new_unique(),
+            decimals,
+            min_bet_amount: bounds.min_bet_amount,
+            max_bet_amount: bounds.max_bet_amount,
+            fee_bps_override: None,
+            enabled: true,
+            creator_bond: 0,
+            bump: 255,
+        }
+    }
+
+    #[test]
+    fn test_collateral_config_size() {
+        let collateral = CollateralConfig {
+            fee_bps_override: Some(25),
+            creator_bond: u64::MAX,
+            ..create_test_collateral(6)
+        };
+        let mut data = Vec::new();
+        collateral.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), CollateralConfig::SIZE);
+    }
+
+    #[test]
+    fn test_bounds_scale_with_decimals() {
+        let base = MarketParams::default();
+        let six = MarketParams::for_decimals(6).unwrap();
+        assert_eq!(six.min_bet_amount, base.min_bet_amount);
+        assert_eq!(six.max_bet_amount, base.max_bet_amount);
+
+        let nine = MarketParams::for_decimals(9).unwrap();
+        assert_eq!(nine.min_bet_amount, base.min_bet_amount * 1_000);
+        assert_eq!(nine.min_liquidity, base.min_liquidity * 1_000);
+        assert_eq!(nine.max_fee_bps, base.max_fee_bps);
+
+        let zero = MarketParams::for_decimals(0).unwrap();
+        assert_eq!(zero.min_bet_amount, 1);
+        assert_eq!(zero.max_bet_amount, 1_000_000);
+
+        assert!(MarketParams::for_decimals(u8::MAX).is_err());
+    }
+
+    #[test]
+    fn test_collateral_bounds_and_fee_override() {
+        let mut collateral = create_test_collateral(9);
+        collateral.min_bet_amount = 1_000_000_000;
+        let bounds = collateral.bounds().unwrap();
+        assert_eq!(bounds.min_bet_amount, 1_000_000_000);
+
+        // A 6-decimal default config is below this mint's minimum bet
+        let config = MarketConfig { min_bet_amount: 100_000, ..MarketConfig::default() };
+        assert!(config.validate(&bounds).is_err());
+
+        assert_eq!(collateral.fee_bps(100), 100);
+        collateral.fee_bps_override = Some(25);
+        assert_eq!(collateral.fee_bps(100), 25);
+    }
+
+    #[test]
+    fn test_collateral_params_validation() {
+        let params = CollateralConfigParams {
+            min_bet_amount: 100_000_000,
+            max_bet_amount: 1_000_000_000_000,
+            fee_bps_override: Some(50),
+            enabled: true,
+            creator_bond: 5_000_000_000,
+        };
+        assert!(params.validate(9).is_ok());
+        // Minimum is below the protocol floor for a 9-decimal mint
+        assert!(CollateralConfigParams { min_bet_amount: 100_000, ..params }.validate(9).is_err());
+        assert!(CollateralConfigParams { fee_bps_override: Some(5_000), ..params }.validate(9).is_err());
+        assert!(CollateralConfigParams { min_bet_amount: 2_000_000_000_000, ..params }.validate(9).is_err());
     }
 }
 
@@ -228,6 +1630,7 @@ mod outcome_tests {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn test_outcome_clone() {
         let outcome = Outcome::Yes;
         let cloned = outcome.clone();
@@ -243,21 +1646,10 @@ mod integration_tests {
     fn test_full_market_lifecycle() {
         // Create market
         let mut market = Market {
-            creator: Pubkey::new_unique(),
-            pyth_feed: Pubkey::new_unique(),
-            target_price: 50000,
-            settle_time: 1234567890,
             yes_pool: 1000,
             no_pool: 500,
-            collateral_mint: Pubkey::new_unique(),
-            is_resolved: false,
-            winning_outcome: None,
-            nonce: 0,
-            bump: 254,
-            resolver_authority: Pubkey::new_unique(),
             total_volume: 1500,
-            fee_bps: 100,
-            final_price: None,
+            ..create_test_market()
         };
 
         // Verify initial state
@@ -280,10 +1672,8 @@ mod integration_tests {
         // Test position payout calculation
         let user_position = Position {
             market: market.creator,
-            better: Pubkey::new_unique(),
             yes_amount: 100,
-            no_amount: 0,
-            claimed: false,
+            ..create_test_position()
         };
 
         let payout = calculate_payout(
@@ -302,21 +1692,7 @@ mod edge_case_tests {
     #[test]
     fn test_zero_pools() {
         let market = Market {
-            creator: Pubkey::new_unique(),
-            pyth_feed: Pubkey::new_unique(),
-            target_price: 50000,
-            settle_time: 1234567890,
-            yes_pool: 0,
-            no_pool: 0,
-            collateral_mint: Pubkey::new_unique(),
-            is_resolved: false,
-            winning_outcome: None,
-            nonce: 0,
-            bump: 254,
-            resolver_authority: Pubkey::new_unique(),
-            total_volume: 0,
-            fee_bps: 100,
-            final_price: None,
+            ..create_test_market()
         };
 
         let (yes_odds, no_odds) = market.calculate_odds();
@@ -327,22 +1703,17 @@ mod edge_case_tests {
 
     #[test]
     fn test_max_values() {
-        let mut market = Market {
-            creator: Pubkey::new_unique(),
-            pyth_feed: Pubkey::new_unique(),
+        let market = Market {
             target_price: i64::MAX,
             settle_time: i64::MAX,
             yes_pool: u64::MAX - 1,
             no_pool: 1,
-            collateral_mint: Pubkey::new_unique(),
-            is_resolved: false,
-            winning_outcome: None,
             nonce: u64::MAX,
             bump: 255,
-            resolver_authority: Pubkey::new_unique(),
             total_volume: u64::MAX,
             fee_bps: 10000, // 100%
             final_price: Some(i64::MAX),
+            ..create_test_market()
         };
 
         // Test that calculations handle max values gracefully
@@ -363,4 +1734,47 @@ mod edge_case_tests {
         let payout = calculate_payout(1, 1000000, 1).unwrap();
         assert_eq!(payout, 1); // Should handle small fractions
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+
+    fn bet(amount: u64) -> BetOrder {
+        BetOrder {
+            outcome: Outcome::Yes,
+            amount,
+            min_expected_payout: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_account_count() {
+        let bets = vec![bet(1_000_000); 3];
+        let expected = 3 * BetOrder::ACCOUNTS_PER_BET;
+        assert!(BetOrder::validate_batch(&bets, expected).is_ok());
+
+        // One account short or over is rejected outright
+        assert!(BetOrder::validate_batch(&bets, expected - 1).is_err());
+        assert!(BetOrder::validate_batch(&bets, expected + 1).is_err());
+        assert!(BetOrder::validate_batch(&bets, 0).is_err());
+    }
+
+    #[test]
+    fn test_batch_size_limits() {
+        assert!(BetOrder::validate_batch(&[], 0).is_err());
+
+        let max = vec![bet(1_000_000); BetOrder::MAX_BATCH_SIZE];
+        assert!(BetOrder::validate_batch(&max, max.len() * BetOrder::ACCOUNTS_PER_BET).is_ok());
+
+        let over = vec![bet(1_000_000); BetOrder::MAX_BATCH_SIZE + 1];
+        assert!(BetOrder::validate_batch(&over, over.len() * BetOrder::ACCOUNTS_PER_BET).is_err());
+    }
+
+    #[test]
+    fn test_batch_rejects_bad_leg_before_any_transfer() {
+        // A zero-amount last leg fails the whole batch up front, so the
+        // valid legs before it never run
+        let bets = [bet(1_000_000), bet(2_000_000), bet(0)];
+        assert!(BetOrder::validate_batch(&bets, bets.len() * BetOrder::ACCOUNTS_PER_BET).is_err());
+    }
+}